@@ -1,39 +1,160 @@
 use proc_macro2::{Ident};
-use syn;
 use syn::parse::ParseStream;
 use syn::token;
 use syn::{Result};
 use std::fmt;
 
+mod kw {
+    syn::custom_keyword!(mode);
+}
+
+/// the mode rules outside of any `mode Name { ... }` block belong to.
+pub const INITIAL_MODE: &str = "Initial";
+
+
+// syntax tokens are kept so the parsed form mirrors the input.
+#[allow(dead_code)]
 #[derive(Clone)]
 pub struct EnumLexer {
     pub attrs: Vec<syn::Attribute>,
     pub vis: syn::Visibility,
     pub enum_token: token::Enum,
     pub ident: Ident,
+    /// `<'a>`, the lifetime of the source that tokens can borrow.
+    pub generics: syn::Generics,
     pub brace_token: token::Brace,
     pub variants: Vec<LexerVariant>,
+    pub modes: Vec<LexerMode>,
+    pub whitespace: Whitespace,
+    /// `#[lossless]`, keep whitespace and ignored rules as trivia of tokens.
+    pub lossless: bool,
+    /// `#[recover]`, read input no rule matches as an `Error` token.
+    pub recover: bool,
+    pub names: Names,
     pub error_type: syn::ItemType,
+    /// `type Context = ..;`, the state handlers see as `ctx`.
+    pub context_type: Option<syn::ItemType>,
+    /// the enum of `#[derive(Lexer)]`, which is the `TokenInner` of the lexer
+    /// instead of a generated one.
+    pub derive: Option<Ident>,
 }
 
+/// what is skipped before each token, set by `#[whitespace = ..]`.
+#[derive(Clone)]
+pub enum Whitespace {
+    /// skip the chars of `char::is_whitespace`.
+    Default,
+    /// `#[whitespace = false]`, skip nothing.
+    Keep,
+    /// `#[whitespace = r"regex"]`, an ignored rule matching the text to skip.
+    Regex(syn::LitStr),
+}
 
+/// the names of the items of the generated module, set by
+/// `#[rename(Token = Tok, parse_str = lex)]`.
+#[derive(Clone)]
+pub struct Names {
+    pub token: Ident,
+    pub token_inner: Ident,
+    pub token_iterator: Ident,
+    pub token_kind: Ident,
+    /// the type implementing `enum_lexer::Lexer`.
+    pub lexer: Ident,
+    /// also the start of `parse_str_with_name` and the others.
+    pub parse_str: Ident,
+    /// the variant `#[recover]` adds for the input no rule matches.
+    pub error: Ident,
+}
+
+impl Names {
+    /// the `parse_str` fn ending with `suffix`, e.g. `parse_str_with_name`.
+    pub(crate) fn parse_str_with(&self, suffix: &str) -> Ident {
+        Ident::new(&format!("{}{}", self.parse_str, suffix), self.parse_str.span())
+    }
+}
+
+/// `Token = Tok` in `#[rename(..)]`.
+struct Rename {
+    from: Ident,
+    to: Ident,
+}
+
+impl syn::parse::Parse for Rename {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let from = input.parse()?;
+        input.parse::<syn::Token![=]>()?;
+        let to = input.parse()?;
+        Ok(Rename { from, to })
+    }
+}
+
+/// `mode Name { variants }`, rules that are only tried in mode `Name`.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct LexerMode {
+    /// `None` to skip the same as the initial mode.
+    pub whitespace: Option<Whitespace>,
+    pub mode_token: kw::mode,
+    pub ident: Ident,
+    pub brace_token: token::Brace,
+    pub variants: Vec<LexerVariant>,
+}
+
+
+#[allow(dead_code)]
 #[derive(Clone)]
 pub enum LexerVariant {
     Single{
         variant: syn::Variant,
+        priority: Option<i32>,
+        describe: Option<syn::LitStr>,
         colon: token::Colon,
         regex: syn::LitStr,
         comma: token::Comma,
     },
     Multiple{
         variant: syn::Variant,
+        priority: Option<i32>,
+        describe: Option<syn::LitStr>,
         colon: token::Colon,
         brace_token: token::Brace,
         entrys: Vec<LexerEntry>,
+    },
+    /// `Group: ("(", ")"), ("[", "]")`, a token tree between brackets.
+    Group{
+        variant: syn::Variant,
+        priority: Option<i32>,
+        describe: Option<syn::LitStr>,
+        colon: token::Colon,
+        pairs: Vec<GroupPair>,
+    },
+}
+
+/// `("(", ")")`, the texts opening and closing a group.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct GroupPair {
+    pub paren_token: token::Paren,
+    pub open: syn::LitStr,
+    pub comma: token::Comma,
+    pub close: syn::LitStr,
+}
+
+impl syn::parse::Parse for GroupPair {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        Ok(GroupPair {
+            paren_token: syn::parenthesized!(content in input),
+            open: content.parse()?,
+            comma: content.parse()?,
+            close: content.parse()?,
+        })
     }
 }
+#[allow(dead_code)]
 #[derive(Clone)]
 pub struct LexerEntry {
+    pub priority: Option<i32>,
     pub regex: syn::LitStr,
     pub fat_arrow_token: token::FatArrow,
     pub body: Option<syn::Expr>,
@@ -42,40 +163,189 @@ pub struct LexerEntry {
 
 impl syn::parse::Parse for EnumLexer {
     fn parse(input: ParseStream) -> Result<Self> {
-        let error_type: syn::ItemType;
-        if input.peek(syn::Token![type]) {
-            error_type = input.parse()?;
-        } else {
-            error_type = syn::parse_quote!{ type LexError = Box<dyn std::error::Error>; }
+        let mut error_type = None;
+        let mut context_type = None;
+        while input.peek(syn::Token![type]) {
+            let item: syn::ItemType = input.parse()?;
+            let slot = if item.ident == "LexError" {
+                &mut error_type
+            } else if item.ident == "Context" {
+                &mut context_type
+            } else {
+                return Err(syn::Error::new(item.ident.span(), "expected `type LexError = ..;` or `type Context = ..;`"));
+            };
+            if slot.is_some() {
+                return Err(syn::Error::new(item.ident.span(), format!("`{}` is already defined", item.ident)));
+            }
+            *slot = Some(item);
         }
-        let attrs = input.call(syn::Attribute::parse_outer)?;
+        let error_type = error_type.unwrap_or_else(|| {
+            syn::parse_quote!{ type LexError = Box<dyn std::error::Error>; }
+        });
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let whitespace = take_whitespace(&mut attrs)?.unwrap_or(Whitespace::Default);
+        let lossless = take_flag(&mut attrs, "lossless")?;
+        let recover = take_flag(&mut attrs, "recover")?;
+        let names = take_rename(&mut attrs)?;
         let vis = input.parse::<syn::Visibility>()?;
         let enum_token = input.parse::<syn::Token![enum]>()?;
         let ident = input.parse::<Ident>()?;
+        let generics = input.parse::<syn::Generics>()?;
+        let lifetimes = generics.params.iter().filter(|p| matches!(p, syn::GenericParam::Lifetime(_))).count();
+        if lifetimes != generics.params.len() || lifetimes > 1 || generics.where_clause.is_some() {
+            return Err(syn::Error::new_spanned(&generics, "only a single lifetime parameter is allowed"));
+        }
 
         let content;
         let brace_token = syn::braced!(content in input);
 
         let mut variants = Vec::new();
+        let mut modes = Vec::<LexerMode>::new();
         while !content.is_empty() {
-            variants.push(content.parse()?);
+            let fork = content.fork();
+            fork.call(syn::Attribute::parse_outer)?;
+            if fork.peek(kw::mode) && fork.peek2(syn::Ident) {
+                let mode: LexerMode = content.parse()?;
+                if mode.ident == INITIAL_MODE || modes.iter().any(|m| m.ident == mode.ident) {
+                    return Err(syn::Error::new(mode.ident.span(), format!("mode `{}` is already defined", mode.ident)));
+                }
+                modes.push(mode);
+            } else {
+                variants.push(content.parse()?);
+            }
         }
 
-        Ok(Self {
+        let lexer = Self {
             attrs,
             vis,
             enum_token,
             ident,
+            generics,
+            brace_token,
+            variants,
+            modes,
+            whitespace,
+            lossless,
+            recover,
+            names,
+            error_type,
+            context_type,
+            derive: None,
+        };
+        if let Some(v) = lexer.all_variants().find(|v| recover && v.variant().ident == lexer.names.error) {
+            return Err(syn::Error::new(v.variant().ident.span(), format!("`{}` is the token of `#[recover]`", lexer.names.error)));
+        }
+        // a variant declared again, e.g. in another mode, is the same variant.
+        let variants: Vec<_> = lexer.all_variants().collect();
+        for (i, v) in variants.iter().enumerate() {
+            let is_group = |v: &LexerVariant| matches!(v, LexerVariant::Group { .. });
+            let first = variants[..i].iter().find(|f| f.variant().ident == v.variant().ident);
+            if let Some(first) = first {
+                if first.variant().fields != v.variant().fields || is_group(first) != is_group(v) {
+                    return Err(syn::Error::new(
+                        v.variant().ident.span(),
+                        format!("`{}` is already declared with other fields", v.variant().ident),
+                    ));
+                }
+            }
+        }
+        Ok(lexer)
+    }
+}
+
+impl syn::parse::Parse for LexerMode {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let whitespace = take_whitespace(&mut attrs)?;
+        if let Some(attr) = attrs.first() {
+            return Err(syn::Error::new_spanned(attr, "only `#[whitespace = ..]` is allowed on a mode"));
+        }
+        let mode_token = input.parse()?;
+        let ident = input.parse()?;
+
+        let content;
+        let brace_token = syn::braced!(content in input);
+
+        let mut variants = Vec::new();
+        while !content.is_empty() {
+            variants.push(content.parse()?);
+        }
+
+        Ok(Self {
+            whitespace,
+            mode_token,
+            ident,
             brace_token,
             variants,
-            error_type
         })
     }
 }
 
+impl EnumLexer {
+    /// the lifetime declared on the lexer.
+    pub(crate) fn lifetime(&self) -> Option<&syn::Lifetime> {
+        self.generics.lifetimes().next().map(|l| &l.lifetime)
+    }
+
+    /// whether `LexError` is the default `Box<dyn std::error::Error>`, then
+    /// the errors of handlers are given the span of their token.
+    pub(crate) fn boxed_error(&self) -> bool {
+        *self.error_type.ty == syn::parse_quote!(Box<dyn std::error::Error>)
+    }
+
+    /// the names of all modes, starting with the initial mode.
+    pub(crate) fn mode_idents(&self) -> Vec<Ident> {
+        std::iter::once(Ident::new(INITIAL_MODE, self.ident.span()))
+            .chain(self.modes.iter().map(|m| m.ident.clone()))
+            .collect()
+    }
+
+    /// every variant, in the initial mode first, then in each mode.
+    pub(crate) fn all_variants(&self) -> impl Iterator<Item=&LexerVariant> {
+        self.variants.iter().chain(self.modes.iter().flat_map(|m| &m.variants))
+    }
+
+    /// what each mode skips, in the order of `mode_idents`.
+    pub(crate) fn whitespaces(&self) -> Vec<Whitespace> {
+        std::iter::once(self.whitespace.clone())
+            .chain(self.modes.iter().map(|m| m.whitespace.clone().unwrap_or_else(|| self.whitespace.clone())))
+            .collect()
+    }
+
+    /// the variants of each mode, in the order of `mode_idents`.
+    pub(crate) fn into_modes(self) -> Vec<Vec<LexerVariant>> {
+        std::iter::once(self.variants)
+            .chain(self.modes.into_iter().map(|m| m.variants))
+            .collect()
+    }
+}
+
 pub(crate) struct LexerMap {
     pub(crate) regex: syn::LitStr,
     pub(crate) expr: syn::Expr,
+    /// when several rules match the same text, the highest priority wins,
+    /// then the one declared first.
+    pub(crate) priority: i32,
+    /// set for the rules that make no token.
+    pub(crate) ignored: Option<Ignored>,
+    /// set for the brackets of a group variant.
+    pub(crate) group: Option<GroupRule>,
+}
+
+/// a bracket of the group variant `variant`.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct GroupRule {
+    pub(crate) variant: Ident,
+    /// the texts of the pair of brackets.
+    pub(crate) delimiters: (String, String),
+    pub(crate) open: bool,
+}
+
+/// the kind of text an ignored rule matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Ignored {
+    Whitespace,
+    Comment,
 }
 
 impl LexerVariant{
@@ -83,32 +353,84 @@ impl LexerVariant{
         use LexerVariant::*;
         match self {
             Single{variant,..} => variant,
-            Multiple{variant,..} => variant
+            Multiple{variant,..} => variant,
+            Group{variant,..} => variant,
         }
         
     }
 
-    pub(crate) fn regex_maps(self) -> Vec<LexerMap> {
+    /// what the variant is called in messages: its `#[describe = ".."]`, its
+    /// doc comment, or the text of its only rule when that is a plain text.
+    pub(crate) fn description(&self) -> Option<String> {
+        let (describe, regex) = match self {
+            LexerVariant::Single{ describe, regex, .. } => (describe, Some(regex)),
+            LexerVariant::Multiple{ describe, entrys, .. } => {
+                (describe, if entrys.len() == 1 { Some(&entrys[0].regex) } else { None })
+            }
+            LexerVariant::Group{ describe, .. } => (describe, None),
+        };
+        if let Some(describe) = describe {
+            return Some(describe.value());
+        }
+        let doc: Vec<_> = self.variant().attrs.iter()
+            .filter(|attr| attr.path.is_ident("doc"))
+            .filter_map(|attr| match attr.parse_meta() {
+                Ok(syn::Meta::NameValue(syn::MetaNameValue{ lit: syn::Lit::Str(lit), ..})) => Some(lit.value()),
+                _ => None,
+            })
+            .collect();
+        let doc = doc.iter().map(|line| line.trim()).filter(|line| !line.is_empty()).collect::<Vec<_>>().join(" ");
+        if !doc.is_empty() {
+            return Some(doc);
+        }
+        let literal = regex?.value().parse::<regex_dfa_gen::ast::AstNode>().ok()?.literal()?;
+        Some(format!("`{}`", literal))
+    }
+
+    /// the rules of the variant, `token_inner` is the name of `TokenInner`.
+    pub(crate) fn regex_maps(self, token_inner: &Ident) -> Vec<LexerMap> {
         match self {
-            LexerVariant::Single{ variant, regex,..} => {
+            LexerVariant::Single{ variant, regex, priority, ..} => {
                 if let syn::Fields::Unit = variant.fields {
+                    let ident = variant.ident;
                     vec![LexerMap {
                         regex,
-                        expr: syn::parse_quote!(TokenInner::#variant)
+                        // qualified, a variant can have the name of an item of the module.
+                        expr: syn::parse_quote!(#token_inner::#ident),
+                        priority: priority.unwrap_or(0),
+                        ignored: None,
+                        group: None,
                     }]
                 } else { vec![] }
             }
-            LexerVariant::Multiple { entrys ,..} => {
+            LexerVariant::Multiple { entrys, priority, ..} => {
                 entrys.into_iter().map(|e|{
+                    let ignored = if e.body.is_none() { Some(Ignored::Comment) } else { None };
                     let expr = e.body.unwrap_or(
                         syn::parse_quote!( {return Ok(None);})
                     );
                     LexerMap {
                         regex: e.regex,
                         expr,
+                        priority: e.priority.or(priority).unwrap_or(0),
+                        ignored,
+                        group: None,
                     }
                 }).collect()
             }
+            LexerVariant::Group { variant, pairs, priority, .. } => {
+                pairs.into_iter().flat_map(|pair| {
+                    let delimiters = (pair.open.value(), pair.close.value());
+                    [(pair.open, true), (pair.close, false)].map(|(text, open)| LexerMap {
+                        regex: syn::LitStr::new(&regex_dfa_gen::ast::escape(&text.value()), text.span()),
+                        // the brackets are read by the iterator, not by a handler.
+                        expr: syn::parse_quote!( { unreachable!() } ),
+                        priority: priority.unwrap_or(0),
+                        ignored: None,
+                        group: Some(GroupRule { variant: variant.ident.clone(), delimiters: delimiters.clone(), open }),
+                    })
+                }).collect()
+            }
         }
     }
 }
@@ -117,11 +439,34 @@ impl syn::parse::Parse for LexerVariant {
     
 
     fn parse(input: ParseStream) -> Result<Self> {
-        let variant = input.parse()?;
+        let mut variant: syn::Variant = input.parse()?;
+        let priority = take_priority(&mut variant.attrs)?;
+        let describe = take_describe(&mut variant.attrs)?;
         let colon = input.parse()?;
-        if input.peek(syn::LitStr) {
+        if input.peek(token::Paren) {
+            if !matches!(variant.fields, syn::Fields::Unit) {
+                return Err(syn::Error::new_spanned(&variant.fields, "a group variant has no fields, its field is the group"));
+            }
+            let mut pairs = vec![input.parse()?];
+            while input.peek(syn::Token![,]) {
+                input.parse::<syn::Token![,]>()?;
+                if !input.peek(token::Paren) {
+                    break;
+                }
+                pairs.push(input.parse()?);
+            }
+            Ok(Self::Group {
+                variant,
+                priority,
+                describe,
+                colon,
+                pairs,
+            })
+        } else if input.peek(syn::LitStr) {
             Ok(Self::Single {
                 variant,
+                priority,
+                describe,
                 colon,
                 regex: input.parse()?,
                 comma: input.parse()?,
@@ -137,6 +482,8 @@ impl syn::parse::Parse for LexerVariant {
 
             Ok(Self::Multiple {
                 variant,
+                priority,
+                describe,
                 colon,
                 brace_token,
                 entrys,
@@ -148,7 +495,13 @@ impl syn::parse::Parse for LexerVariant {
 impl syn::parse::Parse for LexerEntry {
     fn parse(input: ParseStream) -> Result<Self> {
         let requires_comma;
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let priority = take_priority(&mut attrs)?;
+        if let Some(attr) = attrs.first() {
+            return Err(syn::Error::new_spanned(attr, "only `#[priority = N]` is allowed on a rule"));
+        }
         Ok(Self {
+            priority,
             regex: input.parse()?,
             fat_arrow_token: input.parse()?,
             body: {
@@ -173,9 +526,148 @@ impl syn::parse::Parse for LexerEntry {
     }
 }
 
+/// remove the `#[describe = ".."]` attribute from `attrs` and return its text.
+pub(crate) fn take_describe(attrs: &mut Vec<syn::Attribute>) -> Result<Option<syn::LitStr>> {
+    let mut describe = None;
+    let mut ret = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident("describe") {
+            return true;
+        }
+        match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue{ lit: syn::Lit::Str(lit), ..})) => {
+                describe = Some(lit);
+            }
+            _ => {
+                ret = Err(syn::Error::new_spanned(attr, "expected `#[describe = \"..\"]`"));
+            }
+        }
+        false
+    });
+    ret.map(|_| describe)
+}
+
+/// remove the `#[priority = N]` attribute from `attrs` and return its value.
+pub(crate) fn take_priority(attrs: &mut Vec<syn::Attribute>) -> Result<Option<i32>> {
+    let mut priority = None;
+    let mut ret = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident("priority") {
+            return true;
+        }
+        match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue{ lit: syn::Lit::Int(lit), ..})) => {
+                match lit.base10_parse() {
+                    Ok(n) => { priority = Some(n); }
+                    Err(e) => { ret = Err(e); }
+                }
+            }
+            _ => {
+                ret = Err(syn::Error::new_spanned(attr, "expected `#[priority = N]`"));
+            }
+        }
+        false
+    });
+    ret.map(|_| priority)
+}
+
+/// remove the `#[whitespace = ..]` attribute from `attrs` and return its value.
+pub(crate) fn take_whitespace(attrs: &mut Vec<syn::Attribute>) -> Result<Option<Whitespace>> {
+    let mut whitespace = None;
+    let mut ret = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident("whitespace") {
+            return true;
+        }
+        match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue{ lit: syn::Lit::Str(lit), ..})) => {
+                whitespace = Some(Whitespace::Regex(lit));
+            }
+            Ok(syn::Meta::NameValue(syn::MetaNameValue{ lit: syn::Lit::Bool(lit), ..})) => {
+                whitespace = Some(if lit.value { Whitespace::Default } else { Whitespace::Keep });
+            }
+            _ => {
+                ret = Err(syn::Error::new_spanned(attr, "expected `#[whitespace = false]` or `#[whitespace = \"regex\"]`"));
+            }
+        }
+        false
+    });
+    ret.map(|_| whitespace)
+}
+
+/// remove the `#[rename(..)]` attributes from `attrs`, and return the names
+/// they give.
+pub(crate) fn take_rename(attrs: &mut Vec<syn::Attribute>) -> Result<Names> {
+    let span = proc_macro2::Span::call_site();
+    let mut names = Names {
+        token: Ident::new("Token", span),
+        token_inner: Ident::new("TokenInner", span),
+        token_iterator: Ident::new("TokenIterator", span),
+        token_kind: Ident::new("TokenKind", span),
+        lexer: Ident::new("Lexer", span),
+        parse_str: Ident::new("parse_str", span),
+        error: Ident::new("Error", span),
+    };
+    let mut renamed = Vec::new();
+    let mut ret = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident("rename") {
+            return true;
+        }
+        let parser = syn::punctuated::Punctuated::<Rename, syn::Token![,]>::parse_terminated;
+        let result = attr.parse_args_with(parser).and_then(|renames| {
+            for Rename { from, to } in renames {
+                let slot = match from.to_string().as_str() {
+                    "Token" => &mut names.token,
+                    "TokenInner" => &mut names.token_inner,
+                    "TokenIterator" => &mut names.token_iterator,
+                    "TokenKind" => &mut names.token_kind,
+                    "Lexer" => &mut names.lexer,
+                    "parse_str" => &mut names.parse_str,
+                    "Error" => &mut names.error,
+                    _ => {
+                        return Err(syn::Error::new(from.span(), "expected `Token`, `TokenInner`, `TokenIterator`, `TokenKind`, `Lexer`, `parse_str` or `Error`"));
+                    }
+                };
+                if renamed.contains(&from) {
+                    return Err(syn::Error::new(from.span(), format!("`{}` is already renamed", from)));
+                }
+                *slot = to;
+                renamed.push(from);
+            }
+            Ok(())
+        });
+        if ret.is_ok() {
+            ret = result;
+        }
+        false
+    });
+    ret.map(|_| names)
+}
+
+/// remove the attribute `#[name]` from `attrs`, and return whether it is found.
+pub(crate) fn take_flag(attrs: &mut Vec<syn::Attribute>, name: &str) -> Result<bool> {
+    let mut found = false;
+    let mut ret = Ok(());
+    attrs.retain(|attr| {
+        if !attr.path.is_ident(name) {
+            return true;
+        }
+        match attr.parse_meta() {
+            Ok(syn::Meta::Path(_)) => { found = true; }
+            _ => {
+                ret = Err(syn::Error::new_spanned(attr, format!("expected `#[{}]`", name)));
+            }
+        }
+        false
+    });
+    ret.map(|_| found)
+}
+
 pub fn requires_terminator(expr: &syn::Expr) -> bool {
     use syn::Expr;
-    match *expr {
+    !matches!(
+        *expr,
         Expr::Unsafe(..)
         | Expr::Block(..)
         | Expr::If(..)
@@ -184,9 +676,8 @@ pub fn requires_terminator(expr: &syn::Expr) -> bool {
         | Expr::Loop(..)
         | Expr::ForLoop(..)
         | Expr::Async(..)
-        | Expr::TryBlock(..) => false,
-        _ => true,
-    }
+        | Expr::TryBlock(..)
+    )
 }
 
 impl fmt::Debug for LexerEntry {
@@ -205,14 +696,27 @@ impl fmt::Debug for LexerVariant {
                 let vec: Vec<_> = entrys.iter().map(|v| format!("{:?}", v)).collect();
                 write!(f, "\t{}(..): {{\n{}\n\t}}", variant.ident, vec.join("\n"))
             }
+            Self::Group{pairs, variant, ..}=> {
+                let vec: Vec<_> = pairs.iter().map(|p| format!("({:?}, {:?})", p.open.value(), p.close.value())).collect();
+                write!(f, "\t{}: {}", variant.ident, vec.join(", "))
+            }
         }
     }
 }
 
+impl fmt::Debug for LexerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let vec: Vec<_> = self.variants.iter().map(|v| format!("\t{:?}", v)).collect();
+        write!(f, "\tmode {} {{\n{}\n\t}}", self.ident, vec.join("\n"))
+    }
+}
+
 impl fmt::Debug for EnumLexer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let vec: Vec<_> = self.variants.iter().map(|v| format!("{:?}", v)).collect();
-        write!(f, "enum {} {{\n{}\n}}", self.ident.to_string(), vec.join("\n"))
+        let vec: Vec<_> = self.variants.iter().map(|v| format!("{:?}", v))
+            .chain(self.modes.iter().map(|m| format!("{:?}", m)))
+            .collect();
+        write!(f, "enum {} {{\n{}\n}}", self.ident, vec.join("\n"))
     }
 }
 
@@ -250,4 +754,219 @@ mod test {
             "enum lexer {\n\tAlpha(..): {\n\t\t\"alpha\" => <expr>\n\t\t\"alpha0\" => <expr>\n\t}\n\tBeta(..): {\n\t\t\"beta\" => <expr>\n\t\t\"beta\" => <expr>\n\t}\n\tGamma(..): gamma\n}"
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn priority() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            enum lexer {
+                #[priority = 2]
+                Alpha: {
+                    "alpha" => Alpha,
+                    #[priority = 3]
+                    "alpha0" => Alpha,
+                }
+                #[derive(Default)]
+                Gamma: "gamma",
+            }
+        "#).unwrap();
+
+        assert_eq!(ast.variants[1].variant().attrs.len(), 1);
+        assert!(ast.variants[0].variant().attrs.is_empty());
+        let token_inner = ast.names.token_inner.clone();
+        let priorities: Vec<_> = ast.variants.into_iter()
+            .flat_map(|v| v.regex_maps(&token_inner))
+            .map(|m| m.priority)
+            .collect();
+        assert_eq!(priorities, vec![2, 3, 0]);
+
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { #[priority] Gamma: "gamma", }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { Gamma: { #[inline] "gamma" => Gamma, } }"#).is_err());
+    }
+
+    #[test]
+    fn modes() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            enum lexer {
+                Quote: "q",
+                mode Str {
+                    Text: "t",
+                }
+                mode: "m",
+            }
+        "#).unwrap();
+        assert_eq!{
+            format!("{:?}", ast),
+            "enum lexer {\n\tQuote(..): q\n\tmode(..): m\n\tmode Str {\n\t\tText(..): t\n\t}\n}"
+        }
+        let modes: Vec<_> = ast.mode_idents().iter().map(|m| m.to_string()).collect();
+        assert_eq!(modes, vec!["Initial", "Str"]);
+        assert_eq!(ast.all_variants().count(), 3);
+
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { mode A { } mode A { } }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { mode Initial { } }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { A: "a", mode Str { A: "b", } }"#).is_ok());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { A(u8): { "a" => A(0), } mode Str { A(u8): { "b" => A(1), } } }"#).is_ok());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { A: "a", mode Str { A(u8): { "b" => A(1), } } }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { A(u8): { "a" => A(0), } mode Str { A(u16): { "b" => A(1), } } }"#).is_err());
+    }
+
+    #[test]
+    fn whitespace() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            #[whitespace = "[ \t]+"]
+            #[derive(Debug)]
+            enum lexer {
+                A: "a",
+                #[whitespace = false]
+                mode Str { }
+                mode Other { }
+            }
+        "#).unwrap();
+        assert_eq!(ast.attrs.len(), 1);
+        let whitespaces: Vec<_> = ast.whitespaces().into_iter().map(|w| match w {
+            Whitespace::Default => "default".to_string(),
+            Whitespace::Keep => "keep".to_string(),
+            Whitespace::Regex(regex) => regex.value(),
+        }).collect();
+        assert_eq!(whitespaces, vec!["[ \t]+", "keep", "[ \t]+"]);
+
+        assert!(syn::parse_str::<EnumLexer>(r#"#[whitespace = 1] enum lexer { }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { #[inline] mode A { } }"#).is_err());
+    }
+
+    #[test]
+    fn lossless() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            #[lossless]
+            enum lexer {
+                A: "a",
+                COMMENTS: { "//.*" => !, }
+            }
+        "#).unwrap();
+        assert!(ast.lossless && ast.attrs.is_empty());
+        let token_inner = ast.names.token_inner.clone();
+        let ignored: Vec<_> = ast.variants.into_iter()
+            .flat_map(|v| v.regex_maps(&token_inner))
+            .map(|m| m.ignored)
+            .collect();
+        assert_eq!(ignored, vec![None, Some(Ignored::Comment)]);
+
+        assert!(syn::parse_str::<EnumLexer>(r#"#[lossless = true] enum lexer { }"#).is_err());
+    }
+
+    #[test]
+    fn rename() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            #[rename(Token = Tok, parse_str = lex)]
+            #[rename(TokenIterator = Tokens, TokenKind = Kind, Lexer = Words)]
+            #[derive(Debug)]
+            pub enum lexer { A: "a", }
+        "#).unwrap();
+        assert!(matches!(ast.vis, syn::Visibility::Public(_)));
+        assert_eq!(ast.attrs.len(), 1);
+        let names = [&ast.names.token, &ast.names.token_inner, &ast.names.token_iterator, &ast.names.token_kind, &ast.names.lexer];
+        assert_eq!(names.map(|n| n.to_string()), ["Tok", "TokenInner", "Tokens", "Kind", "Words"]);
+        assert_eq!(ast.names.parse_str_with("_with_name"), "lex_with_name");
+
+        assert!(syn::parse_str::<EnumLexer>(r#"#[rename(Span = S)] enum lexer { }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"#[rename(Token = A, Token = B)] enum lexer { }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"#[rename(Token)] enum lexer { }"#).is_err());
+    }
+
+    #[test]
+    fn recover() {
+        let ast: EnumLexer = syn::parse_str(r#"#[recover] enum lexer { A: "a", }"#).unwrap();
+        assert!(ast.recover && !ast.lossless && ast.attrs.is_empty());
+
+        assert!(syn::parse_str::<EnumLexer>(r#"#[recover(all)] enum lexer { }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"#[recover] enum lexer { mode B { Error: "e", } }"#).is_err());
+        let ast: EnumLexer = syn::parse_str(r#"#[recover] #[rename(Error = Unknown)] enum lexer { Error: "e", }"#).unwrap();
+        assert_eq!(ast.names.error, "Unknown");
+        assert!(syn::parse_str::<EnumLexer>(r#"#[recover] #[rename(Error = Unknown)] enum lexer { Unknown: "u", }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { Error: "e", }"#).is_ok());
+    }
+
+    #[test]
+    fn types() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            type Context = Vec<String>;
+            type LexError = String;
+            enum lexer { }
+        "#).unwrap();
+        assert_eq!(ast.error_type.ident, "LexError");
+        assert_eq!(ast.context_type.unwrap().ident, "Context");
+
+        let ast: EnumLexer = syn::parse_str(r#"enum lexer { }"#).unwrap();
+        assert!(ast.context_type.is_none());
+
+        assert!(syn::parse_str::<EnumLexer>(r#"type Other = u8; enum lexer { }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"type Context = u8; type Context = u8; enum lexer { }"#).is_err());
+    }
+
+    #[test]
+    fn lifetime() {
+        let ast: EnumLexer = syn::parse_str(r#"enum lexer<'src> { Ident(&'src str): { "a" => Ident(text), } }"#).unwrap();
+        assert_eq!(ast.lifetime().unwrap().ident, "src");
+        let ast: EnumLexer = syn::parse_str(r#"enum lexer { A: "a", }"#).unwrap();
+        assert!(ast.lifetime().is_none());
+
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer<T> { }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer<'a, 'b> { }"#).is_err());
+    }
+
+    #[test]
+    fn describe() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            enum lexer {
+                Def: r"def",
+                Arrow: r"\-\->",
+                /// an identifier,
+                /// like `a`
+                Ident: r"\w+",
+                #[describe = "integer literal"]
+                /// the doc comment is not used.
+                LitInt: { r"\d+" => LitInt, }
+                Op: { r"\+" => Op, r"\-" => Op, }
+            }
+        "#).unwrap();
+        assert!(ast.variants[3].variant().attrs.iter().all(|attr| !attr.path.is_ident("describe")));
+        let descriptions: Vec<_> = ast.variants.iter().map(|v| v.description()).collect();
+        assert_eq!(descriptions, [
+            Some("`def`".to_string()),
+            Some("`-->`".to_string()),
+            Some("an identifier, like `a`".to_string()),
+            Some("integer literal".to_string()),
+            None,
+        ]);
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { #[describe = 1] A: "a", }"#).is_err());
+    }
+
+    #[test]
+    fn group() {
+        let ast: EnumLexer = syn::parse_str(r#"
+            enum lexer {
+                Group: ("(", ")"), ("[", "]"),
+                Block: ("begin", "end")
+                A: "a",
+            }
+        "#).unwrap();
+        assert_eq!(ast.variants.len(), 3);
+        let token_inner = ast.names.token_inner.clone();
+        let maps: Vec<_> = ast.variants.into_iter().flat_map(|v| v.regex_maps(&token_inner)).collect();
+        let rules: Vec<_> = maps.iter()
+            .filter_map(|m| m.group.as_ref().map(|g| (m.regex.value(), g.variant.to_string(), g.open)))
+            .collect();
+        assert_eq!(rules, [
+            (r"\(".to_string(), "Group".to_string(), true),
+            (r"\)".to_string(), "Group".to_string(), false),
+            (r"\[".to_string(), "Group".to_string(), true),
+            (r"\]".to_string(), "Group".to_string(), false),
+            ("begin".to_string(), "Block".to_string(), true),
+            ("end".to_string(), "Block".to_string(), false),
+        ]);
+        assert!(maps[6].group.is_none());
+
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { Group(u8): ("(", ")"), }"#).is_err());
+        assert!(syn::parse_str::<EnumLexer>(r#"enum lexer { Group: ("("), }"#).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+//! `#[derive(Lexer)]`, the rules of a lexer written as attributes of an enum.
+//!
+//! the enum is read into an `EnumLexer`, like the one `enum_lexer!` parses,
+//! so both make the same lexer. the enum itself is the `TokenInner` of it.
+
+use crate::ast::{self, EnumLexer, LexerEntry, LexerVariant, Whitespace};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::Result;
+
+/// the rule of `#[token("text", callback)]` or `#[regex(r"regex", callback)]`.
+struct Rule {
+    regex: syn::LitStr,
+    callback: Option<syn::Expr>,
+}
+
+impl Rule {
+    fn parse(attr: &syn::Attribute, token: bool) -> Result<Self> {
+        attr.parse_args_with(|input: syn::parse::ParseStream| {
+            let lit: syn::LitStr = input.parse()?;
+            let regex = if token {
+                syn::LitStr::new(&regex_dfa_gen::ast::escape(&lit.value()), lit.span())
+            } else {
+                lit
+            };
+            let callback = if input.is_empty() {
+                None
+            } else {
+                input.parse::<syn::Token![,]>()?;
+                Some(input.parse()?)
+            };
+            Ok(Rule { regex, callback })
+        })
+    }
+}
+
+/// the `EnumLexer` of the enum `input`.
+pub(crate) fn lexer(input: syn::DeriveInput) -> Result<EnumLexer> {
+    let data = match input.data {
+        syn::Data::Enum(data) => data,
+        _ => { return Err(syn::Error::new(input.ident.span(), "`Lexer` can only be derived for an enum")); }
+    };
+    let ident = &input.ident;
+    let module_ident = Ident::new(&snake_case(&ident.to_string()), ident.span());
+    let mut attrs = input.attrs;
+    let whitespace = ast::take_whitespace(&mut attrs)?.unwrap_or(Whitespace::Default);
+    let lossless = ast::take_flag(&mut attrs, "lossless")?;
+    let recover = ast::take_flag(&mut attrs, "recover")?;
+    let names = ast::take_rename(&mut attrs)?;
+
+    let mut skips = Vec::new();
+    let mut module = None;
+    let mut error_type = None;
+    let mut context_type = None;
+    let mut derives = Vec::new();
+    for attr in &attrs {
+        if attr.path.is_ident("skip") {
+            skips.push(LexerEntry {
+                priority: None,
+                regex: attr.parse_args()?,
+                fat_arrow_token: Default::default(),
+                body: None,
+                comma: None,
+            });
+        } else if attr.path.is_ident("module") {
+            module = Some(attr.parse_args::<Ident>()?);
+        } else if attr.path.is_ident("lex_error") {
+            let ty: syn::Type = attr.parse_args()?;
+            error_type = Some(syn::parse_quote! { type LexError = #ty; });
+        } else if attr.path.is_ident("context") {
+            let ty: syn::Type = attr.parse_args()?;
+            context_type = Some(syn::parse_quote! { type Context = #ty; });
+        } else if attr.path.is_ident("token_derive") {
+            // a derive does not see the derives of the enum, so the ones of
+            // `Token` are given apart.
+            let paths = attr.parse_args_with(syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)?;
+            derives.extend(paths);
+        }
+    }
+
+    let mut variants = Vec::new();
+    for variant in data.variants {
+        if let Some(variant) = lexer_variant(variant, &names.token_inner, if recover { Some(&names.error) } else { None })? {
+            variants.push(variant);
+        }
+    }
+    if !skips.is_empty() {
+        variants.push(LexerVariant::Multiple {
+            variant: syn::parse_quote! { COMMENTS },
+            priority: None,
+            describe: None,
+            colon: Default::default(),
+            brace_token: Default::default(),
+            entrys: skips,
+        });
+    }
+
+    Ok(EnumLexer {
+        attrs: if derives.is_empty() { vec![] } else { vec![syn::parse_quote! { #[derive( #( #derives ),* )] }] },
+        vis: input.vis,
+        enum_token: data.enum_token,
+        ident: module.unwrap_or(module_ident),
+        generics: input.generics,
+        brace_token: data.brace_token,
+        variants,
+        modes: Vec::new(),
+        whitespace,
+        lossless,
+        recover,
+        names,
+        error_type: error_type.unwrap_or_else(|| syn::parse_quote! { type LexError = Box<dyn std::error::Error>; }),
+        context_type,
+        derive: Some(input.ident),
+    })
+}
+
+/// the rules of `variant` of `token_inner`, `None` for the variant `recover`
+/// of `#[recover]`.
+fn lexer_variant(mut variant: syn::Variant, token_inner: &Ident, recover: Option<&Ident>) -> Result<Option<LexerVariant>> {
+    let priority = ast::take_priority(&mut variant.attrs)?;
+    let describe = ast::take_describe(&mut variant.attrs)?;
+    let mut rules = Vec::new();
+    let mut pairs = Vec::new();
+    for attr in &variant.attrs {
+        if attr.path.is_ident("token") {
+            rules.push(Rule::parse(attr, true)?);
+        } else if attr.path.is_ident("regex") {
+            rules.push(Rule::parse(attr, false)?);
+        } else if attr.path.is_ident("group") {
+            pairs.push(attr.parse_args_with(|input: syn::parse::ParseStream| {
+                Ok(ast::GroupPair {
+                    paren_token: Default::default(),
+                    open: input.parse()?,
+                    comma: input.parse()?,
+                    close: input.parse()?,
+                })
+            })?);
+        }
+    }
+    if !pairs.is_empty() {
+        if !rules.is_empty() {
+            return Err(syn::Error::new(variant.ident.span(), "a group variant cannot have other rules"));
+        }
+        if !matches!(&variant.fields, syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1) {
+            return Err(syn::Error::new(variant.ident.span(), "a group variant has a single field, the `enum_lexer::Group`"));
+        }
+        return Ok(Some(LexerVariant::Group {
+            variant,
+            priority,
+            describe,
+            colon: Default::default(),
+            pairs,
+        }));
+    }
+    if rules.is_empty() {
+        if recover.is_some_and(|error| variant.ident == *error) {
+            return Ok(None);
+        }
+        return Err(syn::Error::new(variant.ident.span(), "expected `#[token(..)]` or `#[regex(..)]` on the variant"));
+    }
+    let unit = match &variant.fields {
+        syn::Fields::Unit => true,
+        syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => false,
+        _ => { return Err(syn::Error::new(variant.ident.span(), "a variant of a lexer can only have a single unnamed field")); }
+    };
+
+    let ident = &variant.ident;
+    // qualified, a variant can have the name of an item of the module.
+    let ident = quote! { #token_inner::#ident };
+    let entrys = rules.into_iter().map(|Rule { regex, callback }| {
+        let value = callback.map(|callback| quote! {
+            enum_lexer::Callback::<_, LexError>::into_result(
+                enum_lexer::Lex::new(&mut iterator.cursor, &mut text, &mut self.span, &mut iterator.ctx).call(#callback)
+            )?
+        });
+        let body = match (value, unit) {
+            (None, true) => quote! { #ident },
+            (None, false) => quote! { #ident(text.into()) },
+            (Some(value), true) => quote! {{ #value; #ident }},
+            (Some(value), false) => quote! { #ident(#value) },
+        };
+        LexerEntry {
+            priority: None,
+            regex,
+            fat_arrow_token: Default::default(),
+            // kept as tokens, so that the names in callbacks are not rewritten.
+            body: Some(syn::Expr::Verbatim(body)),
+            comma: None,
+        }
+    }).collect();
+    Ok(Some(LexerVariant::Multiple {
+        variant,
+        priority,
+        describe,
+        colon: Default::default(),
+        brace_token: Default::default(),
+        entrys,
+    }))
+}
+
+/// `TokenKind` to `token_kind`, the default name of the module.
+fn snake_case(name: &str) -> String {
+    let mut ret = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            ret.push('_');
+        }
+        ret.extend(c.to_lowercase());
+    }
+    ret
+}
+
+/// the tokens of `#[derive(Lexer)]` on `input`.
+pub(crate) fn derive(input: syn::DeriveInput) -> TokenStream {
+    let lexer = match lexer(input) {
+        Ok(lexer) => lexer,
+        Err(e) => { return e.to_compile_error(); }
+    };
+    match crate::gen::generate(lexer, false) {
+        Ok(stream) => stream,
+        Err(e) => e.to_compile_error(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lexer_str(src: &str) -> Result<EnumLexer> {
+        lexer(syn::parse_str(src).unwrap())
+    }
+
+    #[test]
+    fn variants() {
+        let lexer = lexer_str(r#"
+            #[token_derive(Debug, PartialEq)]
+            #[skip(r"//.*")]
+            #[recover]
+            pub enum Tok<'a> {
+                #[token("+")]
+                #[token("-")]
+                Op,
+                #[regex(r"[0-9]+", |lex| lex.text().parse())]
+                #[priority = 1]
+                Int(u32),
+                #[regex(r"\w+")]
+                Ident(&'a str),
+                Error(&'a str),
+            }
+        "#).unwrap();
+        assert_eq!(lexer.ident, "tok");
+        assert_eq!(lexer.derive.as_ref().unwrap(), "Tok");
+        let attrs = &lexer.attrs;
+        assert_eq!(quote!(#(#attrs)*).to_string(), quote!(#[derive(Debug, PartialEq)]).to_string());
+
+        let token_inner = lexer.names.token_inner.clone();
+        let maps: Vec<_> = lexer.variants.into_iter().flat_map(|v| v.regex_maps(&token_inner)).collect();
+        let regexes: Vec<_> = maps.iter().map(|m| (m.regex.value(), m.priority, m.ignored)).collect();
+        assert_eq!(regexes, vec![
+            (r"\+".to_string(), 0, None),
+            (r"\-".to_string(), 0, None),
+            (r"[0-9]+".to_string(), 1, None),
+            (r"\w+".to_string(), 0, None),
+            (r"//.*".to_string(), 0, Some(ast::Ignored::Comment)),
+        ]);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(lexer_str("struct Tok;").is_err());
+        assert!(lexer_str("enum Tok { A }").is_err());
+        assert!(lexer_str("enum Tok { Error }").is_err());
+        assert!(lexer_str(r#"enum Tok { #[token("a")] A(u8, u8) }"#).is_err());
+        assert!(lexer_str(r#"enum Tok { #[token("a")] A { a: u8 } }"#).is_err());
+        assert!(lexer_str(r#"enum Tok { #[token] A }"#).is_err());
+        assert!(lexer_str(r#"enum Tok { #[group("(", ")")] A }"#).is_err());
+        assert!(lexer_str(r#"enum Tok { #[group("(", ")")] #[token("a")] A(G) }"#).is_err());
+        assert!(lexer_str(r#"enum Tok { #[group("(")] A(G) }"#).is_err());
+        assert!(lexer_str(r#"enum Tok { #[group("(", ")")] #[group("[", "]")] A(G) }"#).is_ok());
+        assert_eq!(lexer_str(r#"#[module(tokens)] enum Tok { #[token("a")] A }"#).unwrap().ident, "tokens");
+        assert_eq!(snake_case("TokenKind"), "token_kind");
+    }
+}
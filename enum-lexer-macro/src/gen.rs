@@ -1,84 +1,317 @@
 
 use crate::ast;
+use crate::handler;
 use regex_dfa_gen::{ 
-    dfa::{ Dfa, DfaState},
+    dfa::{ Dfa, DfaBuilder, DfaState },
     nfa::{ NfaBuilder},
     ast::{ AstNode, Error as RegexError},
+    set::{ CharRange, char_bounds },
 };
 use thiserror::Error;
 
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::{ quote, format_ident};
-use std::{ops::Range};
 use syn::{LitInt, LitChar};
 
 #[derive(Debug, Error)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
-    #[error("regex parse error {0} at {1}")]
-    RegexError(RegexError, String)
+    #[error("regex parse error {0} at {}", .1.value())]
+    RegexError(RegexError, syn::LitStr),
+    #[error("regex {:?} matches the empty string", .0.value())]
+    EmptyMatch(syn::LitStr),
+    #[error("{}", shadowed_message(.regex, .by))]
+    ShadowedRule {
+        regex: syn::LitStr,
+        by: Option<(syn::LitStr, String)>,
+    },
 }
 
-type Result<T> = std::result::Result<T, Error>;
+fn shadowed_message(regex: &syn::LitStr, by: &Option<(syn::LitStr, String)>) -> String {
+    match by {
+        Some((by, example)) => format!(
+            "regex {:?} can never match, it is shadowed by {:?} (both match {:?})",
+            regex.value(), by.value(), example
+        ),
+        None => format!("regex {:?} can never match", regex.value()),
+    }
+}
+
+impl Error {
+    /// where the error should be reported.
+    pub fn span(&self) -> Span {
+        match self {
+            Error::RegexError(e, regex) => regex_span(regex, e.pos()),
+            Error::EmptyMatch(regex) => regex.span(),
+            Error::ShadowedRule{ regex, .. } => regex.span(),
+        }
+    }
+}
+
+/// the span of the char at `pos` in the `regex` literal.
+///
+/// falls back to the whole literal when the compiler cannot point inside
+/// literals, or when escapes make the source differ from the value.
+fn regex_span(regex: &syn::LitStr, pos: Option<usize>) -> Span {
+    let whole = regex.span();
+    let pos = match pos {
+        Some(pos) => pos,
+        None => { return whole; }
+    };
+    let repr = regex.token().to_string();
+    let value = regex.value();
+    let quote = match repr.find('"') {
+        Some(quote) if repr.starts_with('r') || !repr.contains('\\') => quote + 1,
+        _ => { return whole; }
+    };
+    let (offset, len) = value.char_indices().nth(pos)
+        .map(|(i, c)| (i, c.len_utf8()))
+        .unwrap_or((value.len(), 1));
+    regex.token()
+        .subspan(quote + offset..quote + offset + len)
+        .unwrap_or(whole)
+}
+
+/// every error found in a lexer, reported together.
+#[derive(Debug)]
+pub struct Errors(pub Vec<Error>);
+
+impl Errors {
+    pub fn to_compile_error(&self) -> TokenStream {
+        let errors = self.0.iter().map(|e| syn::Error::new(e.span(), e).to_compile_error());
+        quote! { #( #errors )* }
+    }
+}
+
+impl From<Error> for Errors {
+    fn from(e: Error) -> Self {
+        Errors(vec![e])
+    }
+}
+
+type Result<T> = std::result::Result<T, Errors>;
 
-fn uses() -> TokenStream {
+fn uses(lossless: bool) -> TokenStream {
+    let trivia = if lossless {
+        quote! {
+            use enum_lexer::Trivia;
+            use enum_lexer::TriviaKind;
+        }
+    } else {
+        quote! {}
+    };
     quote! {
         use super::*;
         use std::ops::Deref;
         use enum_lexer::Span;
         use enum_lexer::Cursor;
+        use enum_lexer::Checkpoint;
+        use enum_lexer::TokenCursor;
         use enum_lexer::SpanError;
-        use enum_lexer::GroupError;
+        use enum_lexer::LexErrorKind;
+        use std::ops::RangeInclusive;
+        #trivia
+    }
+}
 
+/// the lifetime of the source, and the generics of `Token` and `TokenInner`.
+fn lifetime(lexer: &ast::EnumLexer) -> (syn::Lifetime, TokenStream) {
+    match lexer.lifetime() {
+        Some(lt) => (lt.clone(), quote! { <#lt> }),
+        None => (syn::Lifetime::new("'a", Span::call_site()), quote! {}),
     }
 }
 
 fn type_definition(lexer: &ast::EnumLexer) -> TokenStream {
+    let ast::Names { token, token_inner, token_iterator, token_kind, .. } = &lexer.names;
     let error_type = &lexer.error_type;
+    let context_type = match &lexer.context_type {
+        Some(context_type) => quote! { pub #context_type },
+        None => quote! { pub type Context = (); },
+    };
     let attrs = &lexer.attrs;
+    let modes = lexer.mode_idents();
+    let (lt, generics) = lifetime(lexer);
+    let (trivia_fields, trivia_state) = if lexer.lossless {
+        (
+            quote! {
+                /// the trivia before the token.
+                pub leading: Vec<Trivia>,
+                /// the trivia after the token on the same line, all the rest after the last token.
+                pub trailing: Vec<Trivia>,
+            },
+            quote! {
+                // trivia read and not given to a token yet.
+                trivia: Vec<Trivia>,
+                // the token read ahead while looking for trailing trivia.
+                pending: Option<Option<Result<ProtoToken<#lt>>>>,
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    let leading = if lexer.lossless { quote! { Vec<Trivia> } } else { quote! { () } };
+
+    // a variant can be declared again in another mode, with the same fields.
+    let mut seen = Vec::new();
+    let lexer_variants = 
+        lexer.all_variants()
+        .filter(|v| v.variant().ident != "COMMENTS")
+        .filter(|v| {
+            let ident = &v.variant().ident;
+            let new = !seen.contains(&ident);
+            seen.push(ident);
+            new
+        })
+        .collect::<Vec<_>>();
+    let variants = lexer_variants.iter().map(|v| match v {
+        // the field of a group variant is the group.
+        ast::LexerVariant::Group { variant, .. } => {
+            let syn::Variant { attrs, ident, .. } = variant;
+            quote! { #(#attrs)* #ident(enum_lexer::Group<#token #generics>) }
+        }
+        _ => { let variant = v.variant(); quote! { #variant } }
+    });
+    let mut kinds: Vec<_> = lexer_variants.iter().map(|v| &v.variant().ident).collect();
+    let mut descriptions: Vec<_> = lexer_variants.iter()
+        .map(|v| v.description().unwrap_or_else(|| v.variant().ident.to_string()))
+        .collect();
+    let error = &lexer.names.error;
+    if lexer.recover {
+        kinds.push(error);
+        descriptions.push("unknown text".to_owned());
+    }
+    let kind_names = kinds.iter().map(|k| k.to_string());
+
+    let recover_variant = if !lexer.recover {
+        quote! {}
+    } else if lexer.lifetime().is_some() {
+        quote! {
+            /// text no rule matches, read by `#[recover]`.
+            #error(&#lt str),
+        }
+    } else {
+        quote! {
+            /// text no rule matches, read by `#[recover]`.
+            #error(String),
+        }
+    };
+
+    let vis = item_visibility(lexer);
+    let inner_definition = match &lexer.derive {
+        // the enum of `#[derive(Lexer)]` is the `TokenInner`, seen from
+        // inside the module as widely as the enum is.
+        Some(ident) => {
+            quote! { #vis use super::#ident as #token_inner; }
+        }
+        None => quote! {
+            #(#attrs)*
+            pub enum #token_inner #generics {
+                #( #variants ,)*
+                #recover_variant
+            }
+        },
+    };
 
-    let variants = 
-        lexer.variants.iter()
-        .map(|v| v.variant())
-        .filter(|v| v.ident.to_string() != "COMMENTS");
-    
     quote! {
         pub #error_type
+        #context_type
 
         #(#attrs)*
-        pub struct Token {
-            pub inner: TokenInner,
+        #vis struct #token #generics {
+            pub inner: #token_inner #generics,
             pub span: Span,
+            #trivia_fields
         }
 
-        #(#attrs)*
-        pub enum TokenInner {
-            #( #variants ,)*
+        #inner_definition
+        use #token_inner::*;
+
+        /// the variants of `TokenInner`, without their fields.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum #token_kind {
+            #( #kinds ,)*
+        }
+
+        impl #token_kind {
+            /// every kind, in the order of the variants.
+            pub const ALL: &'static [#token_kind] = &[ #( #token_kind::#kinds ,)* ];
+
+            /// the name of the variant.
+            pub fn name(self) -> &'static str {
+                match self {
+                    #( #token_kind::#kinds => #kind_names ,)*
+                }
+            }
+
+            /// what the kind is called in messages, e.g. `` `let` `` or
+            /// "integer literal".
+            pub fn describe(self) -> &'static str {
+                match self {
+                    #( #token_kind::#kinds => #descriptions ,)*
+                }
+            }
+        }
+
+        impl std::fmt::Display for #token_kind {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.describe())
+            }
+        }
+
+        /// shows the description of the kind of the token.
+        impl #generics std::fmt::Display for #token #generics {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.inner.kind().describe())
+            }
+        }
+
+        impl #generics #token_inner #generics {
+            pub fn kind(&self) -> #token_kind {
+                match self {
+                    #( #token_inner::#kinds { .. } => #token_kind::#kinds ,)*
+                }
+            }
+        }
+
+        /// the set of rules a `TokenIterator` reads tokens with.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Mode {
+            #( #modes ,)*
         }
-        use TokenInner::*;
 
         // #[derive(Debug, Clone, Eq, PartialEq)]
         enum StateNext {
             Next(usize),
             Final(usize),
+            Fail,
             End
         }
 
-        struct ProtoToken{
+        struct ProtoToken<#lt> {
             end_num: usize,
-            text: Option<String>,
+            text: &#lt str,
             span: Span,
         }
 
         pub type Result<T> = std::result::Result<T, LexError>;
         
         // #[derive(Debug, Clone)]
-        pub struct TokenIterator<'a> {
-            cursor: Cursor<'a>,
-            // state: usize,
+        #vis struct #token_iterator<#lt> {
+            cursor: Cursor<#lt>,
+            mode: Mode,
+            mode_stack: Vec<Mode>,
+            ctx: Context,
+            // the groups opened and not closed yet, the innermost last: the
+            // pair of brackets, the opening span, the tokens read in the
+            // group and the trivia before it. they are kept after an error,
+            // so the group can still be closed.
+            groups: Vec<(usize, Span, Vec<#token #generics>, #leading)>,
+            #trivia_state
         }
 
-        impl Deref for Token {
-            type Target = TokenInner;
+        impl #generics Deref for #token #generics {
+            type Target = #token_inner #generics;
 
             fn deref(&self) -> &Self::Target {
                 &self.inner
@@ -87,89 +320,170 @@ fn type_definition(lexer: &ast::EnumLexer) -> TokenStream {
     }
 }
 
-fn get_dfa(lexer: ast::EnumLexer, test: bool) -> Result<(Vec<ast::LexerMap>, Dfa)> {
-    let vec: Vec<_> = lexer.variants.into_iter().flat_map(|v| v.regex_maps()).collect();
+/// the visibility of `Token`, `TokenIterator` and `Lexer`: a trait impl on
+/// them can't leak the `TokenInner`, so they are no more visible than it is.
+fn item_visibility(lexer: &ast::EnumLexer) -> TokenStream {
+    if lexer.derive.is_none() {
+        return quote! { pub };
+    }
+    // the visibility of the enum, written from inside the generated module,
+    // one level below the enum.
+    match &lexer.vis {
+        syn::Visibility::Public(_) => quote! { pub },
+        syn::Visibility::Crate(_) => quote! { pub(crate) },
+        syn::Visibility::Inherited => quote! { pub(super) },
+        syn::Visibility::Restricted(restricted) => {
+            let path = &restricted.path;
+            match path.segments.first() {
+                Some(first) if first.ident == "crate" => quote! { pub(in #path) },
+                // `self` of the enum is `super` of the module.
+                Some(first) if first.ident == "self" => {
+                    let rest = path.segments.iter().skip(1);
+                    quote! { pub(in super #(::#rest)*) }
+                }
+                _ => quote! { pub(in super::#path) },
+            }
+        }
+    }
+}
+
+/// build a dfa for each mode. the `end_num` of a rule is its index in the
+/// returned maps, across all modes.
+fn get_dfa(lexer: ast::EnumLexer, test: bool) -> Result<(Vec<ast::LexerMap>, Vec<Dfa>)> {
+    let whitespaces = lexer.whitespaces();
+    let token_inner = lexer.names.token_inner.clone();
+    let mut vec = Vec::new();
+    let mut mode_of = Vec::new();
+    for (mode, variants) in lexer.into_modes().into_iter().enumerate() {
+        for map in variants.into_iter().flat_map(|v| v.regex_maps(&token_inner)) {
+            vec.push(map);
+            mode_of.push(mode);
+        }
+        // a whitespace regex is an ignored rule after the others.
+        if let ast::Whitespace::Regex(regex) = &whitespaces[mode] {
+            vec.push(ast::LexerMap {
+                regex: regex.clone(),
+                expr: syn::parse_quote!( {return Ok(None);} ),
+                priority: 0,
+                ignored: Some(ast::Ignored::Whitespace),
+                group: None,
+            });
+            mode_of.push(mode);
+        }
+    }
+    let modes_len = mode_of.last().map_or(1, |m| m + 1);
 
-    let asts: Result<Vec<AstNode>> = vec.iter().map(|m| 
+    let mut errors = Vec::new();
+    let asts: Vec<AstNode> = vec.iter().filter_map(|m| {
         m.regex.value().parse::<AstNode>()
-            .map_err(|e| Error::RegexError(e, m.regex.value()))
-    ).collect();
-    let asts = asts?;
-
-    let mut nfabuilder = NfaBuilder::new();
-
-    let nfa_nodes: Vec<_> = asts.iter().enumerate()
-        .map(|(i, a)| {
-            let ret = nfabuilder.from_ast(a);
-            nfabuilder.set_end(&ret, i);
-            ret
-        }).collect();
-    
-    let nfa_node = nfabuilder.options(nfa_nodes);
-    let nfa = nfabuilder.to_nfa(nfa_node);
+            .map_err(|e| errors.push(Error::RegexError(e, m.regex.clone())))
+            .ok()
+    }).collect();
+    if !errors.is_empty() {
+        return Err(Errors(errors));
+    }
+
+    let nfas: Vec<_> = (0..modes_len).map(|mode| {
+        let mut nfabuilder = NfaBuilder::new();
+
+        let nfa_nodes: Vec<_> = asts.iter().zip(&vec).enumerate()
+            .filter(|(i, _)| mode_of[*i] == mode)
+            .map(|(i, (a, m))| {
+                let ret = nfabuilder.from_ast(a);
+                if ret.is_nullable() {
+                    errors.push(Error::EmptyMatch(m.regex.clone()));
+                }
+                nfabuilder.set_end_with_priority(&ret, i, m.priority);
+                ret
+            }).collect();
+
+        let nfa_node = nfabuilder.options(nfa_nodes);
+        nfabuilder.to_nfa(nfa_node)
+    }).collect();
+    if !errors.is_empty() {
+        return Err(Errors(errors));
+    }
 
-    let dfa = Dfa::from_nfa(&nfa).opt();
+    let builders: Vec<_> = nfas.iter().map(DfaBuilder::from_nfa).collect();
+    let shadowed: Vec<_> = builders.iter().flat_map(|b| b.shadowed()).map(|shadowed| {
+        Error::ShadowedRule {
+            regex: vec[shadowed.end_num].regex.clone(),
+            by: shadowed.by.map(|(by, example)| (vec[by].regex.clone(), example)),
+        }
+    }).collect();
+    if !shadowed.is_empty() {
+        return Err(Errors(shadowed));
+    }
+    let dfas: Vec<_> = builders.into_iter().map(|b| b.to_dfa().opt()).collect();
     
     if test {
-        let mut f = std::fs::File::create("dfa.dot").unwrap();
-        dfa.render_to(&mut f).expect("msg");
+        for (mode, dfa) in dfas.iter().enumerate() {
+            let name = if mode == 0 { "dfa.dot".to_string() } else { format!("dfa_{}.dot", mode) };
+            let mut f = std::fs::File::create(name).unwrap();
+            dfa.render_to(&mut f).expect("msg");
+        }
     }
     
     
-    Ok((vec, dfa))
+    Ok((vec, dfas))
 }
 
-fn dfa_arc(range: Range<char>, state: usize, is_greedy: bool, end_num: Option<usize>) -> TokenStream {
-    let start = LitChar::new(range.start, Span::call_site());
-    let end = LitChar::new(range.end, Span::call_site());
+fn dfa_arc(range: CharRange, state: usize, is_greedy: bool, end_num: Option<usize>) -> TokenStream {
+    let (start, end) = match char_bounds(&range) {
+        Some(bounds) => bounds.into_inner(),
+        None => { return TokenStream::new(); }
+    };
+    let start = LitChar::new(start, Span::call_site());
+    let end = LitChar::new(end, Span::call_site());
 
-    if !is_greedy && end_num.is_some() {
-        let end_num = end_num.unwrap();
-        quote! {
-            Some(ch @ #start..#end) => {
-                Ok(StateNext::Final(#end_num))
+    match end_num {
+        Some(end_num) if !is_greedy => quote! {
+            Some(#start..=#end) => {
+                StateNext::Final(#end_num)
             }
-        }
-    } else {
-        quote! {
-            Some(ch @ #start..#end) => {
+        },
+        _ => quote! {
+            Some(#start..=#end) => {
                 self.cursor.next();
-                Ok(StateNext::Next(#state))
+                StateNext::Next(#state)
             }
-        }
+        },
     }
-
 }
 
-fn dfa_state(i: usize, state: &DfaState) -> TokenStream {
-    let fn_ident = format_ident!("dfa_state_{}", i);
+fn dfa_state(mode: usize, i: usize, state: &DfaState) -> TokenStream {
+    let fn_ident = format_ident!("dfa_{}_state_{}", mode, i);
     let DfaState{table, end_num} = state;
 
-    let streams: _ = table.iter().map(|(range, i, is_greedy)| {
-        dfa_arc(range.clone(), *i, *is_greedy, end_num.clone())
+    let streams = table.iter().map(|(range, i, is_greedy)| {
+        dfa_arc(range.clone(), *i, *is_greedy, *end_num)
     });
 
-    let others = if let Some(end_num) = end_num {
+    // an accepting state saves its position, so a longer match that fails
+    // later can fall back to it.
+    let (last_final, save, others) = if let Some(end_num) = end_num {
         let end_num = *end_num;
-        quote!{ Ok(StateNext::Final( #end_num )) }
-    } else { 
-        quote!{
-            let (_, span) = self.cursor.get_token();
-            Err(SpanError(span).into())
-        }
+        (
+            quote!{ last_final },
+            quote!{ *last_final = Some((#end_num, self.cursor.checkpoint())); },
+            quote!{ StateNext::Final( #end_num ) },
+        )
+    } else {
+        (quote!{ _ }, quote!{}, quote!{ StateNext::Fail })
     };
 
-    let end = if let Some(end_num) = end_num {
-        let end_num = *end_num;
-        quote!{ Ok(StateNext::Final( #end_num )) }
-    } else { 
-        quote!{ Ok(StateNext::End) }
+    // running out of input before the first char is the end of tokens.
+    let end = if end_num.is_none() && i == 0 {
+        quote!{ StateNext::End }
+    } else {
+        others.clone()
     };
 
     quote! {
         #[inline(always)]
-        fn #fn_ident (&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+        fn #fn_ident (&mut self, #last_final: &mut Option<(usize, Checkpoint)>) -> StateNext {
+            #save
+            match self.cursor.peek() {
                 #( #streams )*
                 None => { #end }
                 _ => { #others }
@@ -184,155 +498,676 @@ fn to_lit_int(i : usize) -> LitInt {
 }
 
 
-fn state_machine(maps: &Vec<ast::LexerMap>, dfa: &Dfa) -> TokenStream {
+/// the chars leaving `state`, as sorted char patterns with adjacent ranges merged.
+fn char_ranges(state: &DfaState) -> Vec<TokenStream> {
+    let mut bounds: Vec<_> = state.table.iter()
+        .filter_map(|(range, _, _)| char_bounds(range))
+        .map(|bounds| bounds.into_inner())
+        .collect();
+    bounds.sort();
+    let mut merged = Vec::<(char, char)>::new();
+    for (start, end) in bounds {
+        match merged.last_mut() {
+            Some(last) if start as u32 <= last.1 as u32 + 1 => { last.1 = last.1.max(end); }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged.into_iter().map(|(start, end)| {
+        let start = LitChar::new(start, Span::call_site());
+        let end = LitChar::new(end, Span::call_site());
+        quote! { #start..=#end }
+    }).collect()
+}
+
+/// the states of the dfa of `mode`, and a fn running it to the end of a token.
+///
+/// with `recover`, the `end_num` of the `Error` token, input no rule matches
+/// is read up to the next char a token can start with.
+fn mode_machine(mode: usize, dfa: &Dfa, recover: Option<usize>, whitespace: &ast::Whitespace) -> TokenStream {
     let len = dfa.states.len();
     let states_num: Vec<_> = (0..len)
-        .map(|i| to_lit_int(i))
+        .map(to_lit_int)
         .collect();
 
     let funcs: Vec<_> = (0..len)
-        .map(|i| format_ident!("dfa_state_{}", i))
+        .map(|i| format_ident!("dfa_{}_state_{}", mode, i))
         .collect();
-    
 
-    let state_arcs = quote! {
-        #(#states_num => self.#funcs(),)*
+    let states = dfa.states.iter().enumerate().map(|(i, s)|{
+        dfa_state(mode, i, s)
+    });
+    let run = format_ident!("run_dfa_{}", mode);
+
+    let (start, fail_fn, on_fail) = match recover {
+        Some(end_num) => {
+            let can_start = format_ident!("can_start_{}", mode);
+            let ranges = char_ranges(&dfa.states[0]);
+            let starts = if ranges.is_empty() {
+                quote! { false }
+            } else {
+                quote! { matches!(c, #( #ranges )|*) }
+            };
+            let skipped = match whitespace {
+                ast::Whitespace::Default => quote! { || c.is_whitespace() },
+                _ => quote! {},
+            };
+            (
+                quote! { let start = self.cursor.checkpoint(); },
+                quote! {
+                    #[inline(always)]
+                    fn #can_start(c: char) -> bool {
+                        #starts #skipped
+                    }
+                },
+                quote! {
+                    // an error token from the first char to the next char a token can start with.
+                    self.cursor.restore(start);
+                    self.cursor.next();
+                    while let Some(c) = self.cursor.peek() {
+                        if Self::#can_start(c) {
+                            break;
+                        }
+                        self.cursor.next();
+                    }
+                    return Some(Ok(#end_num));
+                },
+            )
+        }
+        None => {
+            let expected = format_ident!("expected_{}", mode);
+            let tables = dfa.states.iter().enumerate().map(|(i, state)| {
+                let i = to_lit_int(i);
+                let ranges = char_ranges(state);
+                quote! {
+                    #i => {
+                        const EXPECTED: &[RangeInclusive<char>] = &[ #( #ranges ),* ];
+                        EXPECTED
+                    }
+                }
+            });
+            (
+                quote! {},
+                quote! {
+                    /// the chars `state` can go on with.
+                    fn #expected(state: usize) -> &'static [RangeInclusive<char>] {
+                        match state {
+                            #( #tables )*
+                            _ => &[],
+                        }
+                    }
+                },
+                quote! {
+                    // the unexpected char is a part of the error, the next token starts after it.
+                    let expected = Self::#expected(cur_state);
+                    let kind = match self.cursor.next() {
+                        Some(ch) => LexErrorKind::UnexpectedChar { ch, expected },
+                        None => LexErrorKind::UnexpectedEof { expected },
+                    };
+                    let (text, span) = self.cursor.get_token();
+                    return Some(Err(SpanError::new(kind, span, text).into()));
+                },
+            )
+        }
     };
 
-    let handlers: _ =  maps.iter().enumerate().map(|(i,m)| {
-        let expr = m.expr.clone();
+    quote! {
+        #( #states )*
+
+        #fail_fn
+
+        #[inline(always)]
+        fn #run(&mut self) -> Option<Result<usize>> {
+            #start
+            let mut cur_state = 0;
+            let mut last_final = None;
+            loop {
+                let result = match cur_state {
+                    #(#states_num => self.#funcs(&mut last_final),)*
+                    _ => { panic!("Unexpected"); }
+                };
+                match result {
+                    StateNext::Next(state) => { cur_state = state; }
+                    StateNext::Final(end_num) => { return Some(Ok(end_num)); }
+                    StateNext::Fail => {
+                        // fall back to the longest prefix that is a token.
+                        if let Some((end_num, checkpoint)) = last_final.take() {
+                            self.cursor.restore(checkpoint);
+                            return Some(Ok(end_num));
+                        }
+                        #on_fail
+                    }
+                    StateNext::End => { return None; }
+                };
+            }
+        }
+    }
+}
+
+fn state_machine(lexer: &ast::EnumLexer, maps: &[ast::LexerMap], dfas: &[Dfa]) -> TokenStream {
+    let ast::Names { token, token_inner, token_iterator, .. } = &lexer.names;
+    let modes = lexer.mode_idents();
+    let whitespaces = lexer.whitespaces();
+    let lossless = lexer.lossless;
+    let (lt, generics) = lifetime(lexer);
+    // the `Error` token of `#[recover]` comes after every rule.
+    let recover = if lexer.recover { Some(maps.len()) } else { None };
+    let machines = dfas.iter().zip(&whitespaces).enumerate()
+        .map(|(mode, (dfa, whitespace))| mode_machine(mode, dfa, recover, whitespace));
+    let runs = (0..dfas.len()).map(|mode| format_ident!("run_dfa_{}", mode));
+    let skips = whitespaces.iter().map(|w| match w {
+        // trivia are cut after each newline, so a token can keep the rest of its line.
+        ast::Whitespace::Default if lossless => quote! {
+            loop {
+                let span = self.cursor.leap_until(|c| !c.is_whitespace() || c == '\n');
+                let span = if self.cursor.peek() == Some('\n') {
+                    span.join(self.cursor.leap()).unwrap()
+                } else {
+                    span
+                };
+                if span.is_empty() {
+                    break;
+                }
+                self.trivia.push(Trivia { kind: TriviaKind::Whitespace, span });
+            }
+        },
+        ast::Whitespace::Default => quote! { self.cursor.leap_until(|c| !c.is_whitespace()); },
+        _ => quote! {},
+    });
+    let Trivia { init, methods, next_fn, take_leading, on_ignored, on_close, make_token, leading } = trivia(maps, lexer, &lt, &generics);
+
+    // the pairs of brackets of the group variants, known by their index here.
+    let mut pairs: Vec<&ast::GroupRule> = Vec::new();
+    let group_rules: Vec<_> = maps.iter().enumerate().filter_map(|(i, m)| {
+        let rule = m.group.as_ref()?;
+        let pair = match pairs.iter().position(|p| p.variant == rule.variant && p.delimiters == rule.delimiters) {
+            Some(pair) => pair,
+            None => {
+                pairs.push(rule);
+                pairs.len() - 1
+            }
+        };
+        let (i, pair, open) = (to_lit_int(i), to_lit_int(pair), rule.open);
+        Some(quote! { #i => Some((#pair, #open)), })
+    }).collect();
+    let delimiters = pairs.iter().map(|p| {
+        let (open, close) = &p.delimiters;
+        quote! { (#open, #close) }
+    });
+    let group_variants = pairs.iter().enumerate().map(|(i, p)| {
+        let (i, variant) = (to_lit_int(i), &p.variant);
+        quote! { #i => #variant(enum_lexer::Group { delimiters: Self::GROUP_DELIMITERS[pair], open, close, tokens }), }
+    });
+
+    let handlers =  maps.iter().enumerate().map(|(i,m)| {
+        let expr = handler::rewrite(m.expr.clone(), i);
         let i = to_lit_int(i);
         quote! { #i => #expr, }
     });
-
-    let states: _ = dfa.states.iter().enumerate().map(|(i, s)|{
-        dfa_state(i, s)
+    // qualified, as an `Error` of `use super::*` would be ambiguous.
+    let recover_handler = recover.map(|i| {
+        let (i, error) = (to_lit_int(i), &lexer.names.error);
+        if lexer.lifetime().is_some() {
+            quote! { #i => #token_inner::#error(text), }
+        } else {
+            quote! { #i => #token_inner::#error(text.into()), }
+        }
     });
+    // a `?` in a handler gives a bare error, which the default `LexError`
+    // wraps with the span of the token.
+    let (handler_error, handler_error_fn) = if lexer.boxed_error() {
+        (
+            quote! { .map_err(|e| self.handler_error(e, proto.span)) },
+            quote! {
+                /// `e` from the handler of the token at `span`, as a `SpanError`.
+                fn handler_error(&self, e: LexError, span: Span) -> LexError {
+                    if e.is::<SpanError>() {
+                        e
+                    } else {
+                        SpanError::new(LexErrorKind::Handler(e), span, self.cursor.text(span)).into()
+                    }
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+    // handlers borrow the text in a lexer with a lifetime and in a derive,
+    // other lexers own it.
+    // `token_text` is set by the `cursor` of a handler shadowing `text`.
+    let token_text = handler::token_text();
+    let text = if lexer.lifetime().is_some() || lexer.derive.is_some() {
+        quote! {
+            #[allow(unused_mut)]
+            let mut text: &#lt str = self.text;
+            #[allow(unused_mut, unused_variables)]
+            let mut #token_text: &#lt str = "";
+        }
+    } else {
+        quote! {
+            #[allow(unused_mut)]
+            let mut text: String = self.text.to_owned();
+            #[allow(unused_mut, unused_variables)]
+            let mut #token_text = String::new();
+        }
+    };
 
     quote! {
-        impl<'a> TokenIterator<'a> {
-            #( #states )*
+        impl<#lt> #token_iterator<#lt> {
+            fn new(cursor: Cursor<#lt>, ctx: Context) -> Self {
+                #token_iterator {
+                    cursor,
+                    mode: Mode::Initial,
+                    mode_stack: Vec::new(),
+                    ctx,
+                    groups: Vec::new(),
+                    #init
+                }
+            }
+
+            /// the context handlers see as `ctx`.
+            pub fn ctx(&self) -> &Context {
+                &self.ctx
+            }
+
+            pub fn ctx_mut(&mut self) -> &mut Context {
+                &mut self.ctx
+            }
+
+            /// take the context back, e.g. after the last token.
+            pub fn into_ctx(self) -> Context {
+                self.ctx
+            }
+
+            /// the mode the next token is read in.
+            pub fn mode(&self) -> Mode {
+                self.mode
+            }
+
+            /// read the next tokens in `mode`, until the matching `pop_mode`.
+            pub fn push_mode(&mut self, mode: Mode) {
+                self.mode_stack.push(self.mode);
+                self.mode = mode;
+            }
+
+            /// go back to the mode before the last `push_mode`, and return the mode left.
+            ///
+            /// returns `None` and keeps the current mode if nothing is pushed.
+            pub fn pop_mode(&mut self) -> Option<Mode> {
+                let mode = self.mode_stack.pop()?;
+                Some(std::mem::replace(&mut self.mode, mode))
+            }
+
+            /// switch to `mode`, without touching the modes pushed.
+            pub fn set_mode(&mut self, mode: Mode) {
+                self.mode = mode;
+            }
+
+            #( #machines )*
 
             #[inline(always)]
-            fn next_proto(&mut self) -> Option<Result<ProtoToken>> {
-                let mut cur_state = 0;
-                self.cursor.leap_until(|c| c != ' ' && c != '\n' && c != '\r' && c != '\t');
-                let end_num = loop {
-                    let result = match cur_state {
-                        #state_arcs
-                        _ => { panic!("Unexpected"); }
-                    };
-                    match result {
-                        Ok(StateNext::Next(state)) => { cur_state = state; }
-                        Ok(StateNext::Final(end_num)) => { break end_num; }
-                        Ok(StateNext::End) => { return None; }
-                        Err(e) => { return Some(Err(e)); }
-                    };
+            fn next_proto(&mut self) -> Option<Result<ProtoToken<#lt>>> {
+                let end_num = match self.mode {
+                    #( Mode::#modes => {
+                        #skips
+                        self.#runs()
+                    } )*
+                };
+                let end_num = match end_num? {
+                    Ok(end_num) => end_num,
+                    Err(e) => { return Some(Err(e)); }
                 };
                 let (text, span) = self.cursor.get_token();
-                let text = Some(text);
                 Some(Ok(ProtoToken{end_num, text, span}))
             }
 
-            pub fn next_until(&mut self, num: usize) -> Result<Vec<Token>> {
-                let mut vec = Vec::new();
+            #methods
+
+            /// the brackets of the groups, by pair.
+            const GROUP_DELIMITERS: &'static [(&'static str, &'static str)] = &[ #( #delimiters ,)* ];
+
+            /// the pair of brackets the rule `end_num` belongs to, and whether it opens the group.
+            fn group_rule(end_num: usize) -> Option<(usize, bool)> {
+                match end_num {
+                    #( #group_rules )*
+                    _ => None,
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn group(pair: usize, open: Span, close: Span, tokens: Vec<#token #generics>) -> #token_inner #generics {
+                match pair {
+                    #( #group_variants )*
+                    _ => unreachable!(),
+                }
+            }
+
+            #handler_error_fn
+
+            /// the token of `proto`, `None` for a rule that makes no token. a
+            /// bracket opening a group reads the whole group. the groups of
+            /// `self.groups` past `base` belong to this call.
+            #[allow(unused_variables)]
+            fn tree(&mut self, mut proto: ProtoToken<#lt>, base: usize) -> Result<Option<#token #generics>> {
                 loop {
-                    let proto = self.next_proto();
-                    let (span, inner) = match proto {
-                        Some(proto) => {
-                            let mut proto = proto?;
-                            if proto.end_num == num {
-                                return Ok(vec);
+                    let made = match Self::group_rule(proto.end_num) {
+                        Some((pair, true)) => {
+                            #take_leading
+                            self.groups.push((pair, proto.span, Vec::new(), #leading));
+                            None
+                        }
+                        Some((pair, false)) => match self.groups[base..].last() {
+                            Some(&(open_pair, ..)) if open_pair == pair => {
+                                let (_, open, tokens, leading) = self.groups.pop().unwrap();
+                                let span = open.join(proto.span).unwrap();
+                                Some((Self::group(pair, open, proto.span, tokens), span, leading))
+                            }
+                            // the group stays open, its bracket may still come.
+                            Some(&(open_pair, open, ..)) => {
+                                let kind = LexErrorKind::MismatchedGroup { open, expected: Self::GROUP_DELIMITERS[open_pair].1 };
+                                return Err(SpanError::new(kind, proto.span, proto.text).into());
+                            }
+                            None => {
+                                return Err(SpanError::new(LexErrorKind::UnexpectedClose, proto.span, proto.text).into());
+                            }
+                        },
+                        None => {
+                            #take_leading
+                            let inner = proto.handlers(self)#handler_error?;
+                            // the handler may have read more of the token.
+                            let span = proto.span;
+                            match inner {
+                                Some(inner) => Some((inner, span, #leading)),
+                                None => {
+                                    #on_ignored
+                                    None
+                                }
                             }
-                            let inner = proto.handlers(self);
-                            (proto.span, inner)
                         }
-                        None => { return Err(GroupError().into()); }
                     };
-                    let inner = match inner? {
-                        Some(inner) => inner,
-                        None => { continue; }
+                    if let Some((inner, span, leading)) = made {
+                        let token = #make_token;
+                        match self.groups[base..].last_mut() {
+                            Some((_, _, tokens, _)) => { tokens.push(token); }
+                            None => { return Ok(Some(token)); }
+                        }
+                    } else if self.groups.len() == base {
+                        return Ok(None);
+                    }
+                    proto = match self.#next_fn() {
+                        Some(proto) => proto?,
+                        None => {
+                            let open = self.groups.last().unwrap().1;
+                            self.groups.truncate(base);
+                            let (text, span) = self.cursor.get_token();
+                            return Err(SpanError::new(LexErrorKind::UnclosedGroup { open }, span, text).into());
+                        }
                     };
-                    vec.push(Token {inner, span});
+                }
+            }
+
+            /// read tokens until the rule `num` closes the group opened at `open`.
+            pub fn next_until(&mut self, num: usize, open: Span) -> Result<Vec<#token #generics>> {
+                let base = self.groups.len();
+                let mut vec = Vec::new();
+                loop {
+                    let proto = match self.#next_fn() {
+                        Some(proto) => proto?,
+                        None => {
+                            let (text, span) = self.cursor.get_token();
+                            return Err(SpanError::new(LexErrorKind::UnclosedGroup { open }, span, text).into());
+                        }
+                    };
+                    if proto.end_num == num {
+                        #on_close
+                        return Ok(vec);
+                    }
+                    match self.tree(proto, base) {
+                        Ok(Some(token)) => vec.push(token),
+                        Ok(None) => {}
+                        // the groups opened in this one go with it.
+                        Err(e) => {
+                            self.groups.truncate(base);
+                            return Err(e);
+                        }
+                    }
                 }
             }
         }
-        impl ProtoToken {
+        impl<#lt> ProtoToken<#lt> {
             #[inline(always)]
             fn same_type(&self, other: &ProtoToken) -> bool {
                 self.end_num == other.end_num
             }
             #[inline(always)]
-            fn handlers(&mut self, iterator: &mut TokenIterator) -> Result<Option<TokenInner>> {
-                
-                let num = self.end_num;
-                let mut read_group = {
-                    || { iterator.next_until(num + 1)}
-                };
-                let text = self.text.take().unwrap();
+            fn handlers(&mut self, iterator: &mut #token_iterator<#lt>) -> Result<Option<#token_inner #generics>> {
+                #text
                 let inner = match self.end_num {
                     #( #handlers )*
+                    #recover_handler
                     _ => { panic!("Unexpected"); }
                 };
                 Ok(Some(inner))
             }
         }
 
-        impl<'a> Iterator for TokenIterator<'a> {
-            type Item = Result<Token>;
-            fn next(&mut self) -> Option<Result<Token>> {
-                use TokenInner::*;
-                let proto = self.next_proto();
-                let (span, inner) = match proto {
-                    Some(Ok(mut proto)) => {
-                        let inner = proto.handlers(self);
-                        (proto.span, inner)
+        impl<#lt> Iterator for #token_iterator<#lt> {
+            type Item = Result<#token #generics>;
+            fn next(&mut self) -> Option<Result<#token #generics>> {
+                loop {
+                    let proto = match self.#next_fn() {
+                        Some(Ok(proto)) => proto,
+                        Some(Err(e)) => { return Some(Err(e)); }
+                        // a group left open after an error.
+                        None => {
+                            let open = self.groups.last()?.1;
+                            self.groups.clear();
+                            let (text, span) = self.cursor.get_token();
+                            return Some(Err(SpanError::new(LexErrorKind::UnclosedGroup { open }, span, text).into()));
+                        }
+                    };
+                    match self.tree(proto, 0) {
+                        Ok(Some(token)) => { return Some(Ok(token)); }
+                        Ok(None) => {}
+                        Err(e) => { return Some(Err(e)); }
                     }
-                    Some(Err(e)) => { return Some(Err(e)); },
-                    None => { return None; }
-                };
-                let inner = match inner {
-                    Ok(Some(inner)) => inner,
-                    Ok(None) => { return self.next(); }
-                    Err(e) => { return Some(Err(e)); }
-                };
-                Some(Ok(Token {inner, span}))
+                }
             }
         }
     }
 }
 
+/// the parts of `TokenIterator` that differ in a `#[lossless]` lexer.
+struct Trivia {
+    init: TokenStream,
+    methods: TokenStream,
+    next_fn: Ident,
+    take_leading: TokenStream,
+    on_ignored: TokenStream,
+    on_close: TokenStream,
+    make_token: TokenStream,
+    /// the trivia kept for a group until it closes.
+    leading: TokenStream,
+}
+
+fn trivia(maps: &[ast::LexerMap], lexer: &ast::EnumLexer, lt: &syn::Lifetime, generics: &TokenStream) -> Trivia {
+    let ast::Names { token, token_inner, .. } = &lexer.names;
+    if !lexer.lossless {
+        return Trivia {
+            init: quote! {},
+            methods: quote! {},
+            next_fn: format_ident!("next_proto"),
+            take_leading: quote! {},
+            on_ignored: quote! {},
+            on_close: quote! {},
+            make_token: quote! { #token { inner, span } },
+            leading: quote! { () },
+        };
+    }
+
+    let kinds = maps.iter().enumerate().filter_map(|(i, m)| {
+        let i = to_lit_int(i);
+        match m.ignored? {
+            ast::Ignored::Whitespace => Some(quote! { #i => Some(TriviaKind::Whitespace), }),
+            ast::Ignored::Comment => Some(quote! { #i => Some(TriviaKind::Comment), }),
+        }
+    });
+
+    Trivia {
+        init: quote! {
+            trivia: Vec::new(),
+            pending: None,
+        },
+        methods: quote! {
+            /// the trivia not given to a token yet. at the end of an input
+            /// without tokens, this is all of the input.
+            pub fn trivia(&self) -> &[Trivia] {
+                &self.trivia
+            }
+
+            /// the next token that is not trivia, the trivia before it are
+            /// pushed to `self.trivia`.
+            fn next_significant(&mut self) -> Option<Result<ProtoToken<#lt>>> {
+                if let Some(pending) = self.pending.take() {
+                    return pending;
+                }
+                loop {
+                    let proto = self.next_proto();
+                    if let Some(Ok(proto)) = &proto {
+                        let kind = match proto.end_num {
+                            #( #kinds )*
+                            _ => None,
+                        };
+                        if let Some(kind) = kind {
+                            self.trivia.push(Trivia { kind, span: proto.span });
+                            continue;
+                        }
+                    }
+                    return proto;
+                }
+            }
+
+            /// a token with the trivia `leading` it, reading ahead for the trivia after it.
+            fn token(&mut self, inner: #token_inner #generics, span: Span, leading: Vec<Trivia>) -> #token #generics {
+                let next = self.next_significant();
+                let line = span.end().line;
+                let len = if next.is_none() {
+                    self.trivia.len()
+                } else {
+                    // the trivia after the token up to the end of its line.
+                    let mut len = 0;
+                    for trivia in &self.trivia {
+                        if trivia.span.start().line != line {
+                            break;
+                        }
+                        len += 1;
+                        if trivia.span.end().line != line {
+                            break;
+                        }
+                    }
+                    len
+                };
+                let trailing = self.trivia.drain(..len).collect();
+                self.pending = Some(next);
+                #token { inner, span, leading, trailing }
+            }
+        },
+        next_fn: format_ident!("next_significant"),
+        take_leading: quote! {
+            let leading = std::mem::take(&mut self.trivia);
+        },
+        // a rule that made no token is a comment.
+        on_ignored: quote! {
+            let mut trivia = leading;
+            trivia.push(Trivia { kind: TriviaKind::Comment, span });
+            trivia.append(&mut self.trivia);
+            self.trivia = trivia;
+        },
+        on_close: quote! {
+            self.trivia.push(Trivia { kind: TriviaKind::GroupClose, span: proto.span });
+        },
+        make_token: quote! { self.token(inner, span, leading) },
+        leading: quote! { leading },
+    }
+}
+
 // thread_local! {
 //     static LEXERMAP: RefCell<Vec<ast::LexerMap>> = RefCell::new(Vec::new());
 // }
 
 pub fn generate(lexer: ast::EnumLexer, test: bool) -> Result<TokenStream> {
     let ident = lexer.ident.clone();
-    let uses = uses();
+    let vis = lexer.vis.clone();
+    let token_iterator = &lexer.names.token_iterator;
+    let parse_str = &lexer.names.parse_str;
+    let with_name = lexer.names.parse_str_with("_with_name");
+    let with_context = lexer.names.parse_str_with("_with_context");
+    let with_name_and_context = lexer.names.parse_str_with("_with_name_and_context");
+    // without a context `()` is used, other contexts must be given.
+    let parse_fns = if lexer.context_type.is_none() {
+        quote! {
+            pub fn #parse_str<'a>(src: &'a str) -> Result<#token_iterator<'a>> {
+                #with_context(src, ())
+            }
+
+            pub fn #with_name<'a>(name: &str, src: &'a str) -> Result<#token_iterator<'a>> {
+                #with_name_and_context(name, src, ())
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let lexer_impl = {
+        let lexer_type = &lexer.names.lexer;
+        let token = &lexer.names.token;
+        let (lt, generics) = lifetime(&lexer);
+        let vis = item_visibility(&lexer);
+        quote! {
+            /// the lexer of this module, for code generic over lexers.
+            #[derive(Debug, Clone, Copy, Default)]
+            #vis struct #lexer_type;
+
+            impl enum_lexer::Lexer for #lexer_type {
+                type Token<#lt> = #token #generics;
+                type Error = LexError;
+                type Context = Context;
+                type Iter<#lt> = #token_iterator<#lt>;
+
+                fn lex_with_context<#lt>(name: &str, src: &#lt str, ctx: Context) -> Result<#token_iterator<#lt>> {
+                    #with_name_and_context(name, src, ctx)
+                }
+            }
+        }
+    };
+    let uses = uses(lexer.lossless);
     let type_definition = type_definition(&lexer);
-    let (maps, dfa) = get_dfa(lexer, test)?;
-    let state_machine = state_machine(&maps, &dfa);
+    let state_machine = {
+        let (maps, dfas) = get_dfa(lexer.clone(), test)?;
+        state_machine(&lexer, &maps, &dfas)
+    };
     
     // LEXERMAP.with(|lm| {
     //     lm.replace(maps)
     // });
     Ok(quote! {
         #[allow(non_snake_case)]
-        mod #ident {
+        #vis mod #ident {
             #uses
 
             #type_definition
 
             #state_machine
 
-            pub fn parse_str<'a>(src: &'a str) -> Result<TokenIterator<'a>> {
-                let cursor = Cursor::new_file("<string>", src);
-                Ok(TokenIterator{ cursor })
+            #parse_fns
+
+            #lexer_impl
+
+            pub fn #with_context<'a>(src: &'a str, ctx: Context) -> Result<#token_iterator<'a>> {
+                #with_name_and_context("<string>", src, ctx)
             }
 
-            pub fn parse_str_with_name<'a>(name: &str, src: &'a str) -> Result<TokenIterator<'a>> {
+            pub fn #with_name_and_context<'a>(name: &str, src: &'a str, ctx: Context) -> Result<#token_iterator<'a>> {
                 let cursor = Cursor::new_file(name, src);
-                Ok(TokenIterator{ cursor })
+                Ok(#token_iterator::new(cursor, ctx))
             }
         }
     })
@@ -345,4 +1180,105 @@ pub fn generate(lexer: ast::EnumLexer, test: bool) -> Result<TokenStream> {
 //             &lm.regex.value() == regex
 //         })
 //     })
-// }
\ No newline at end of file
+// }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn generate_str(src: &str) -> Result<TokenStream> {
+        generate(syn::parse_str(src).unwrap(), false)
+    }
+
+    #[test]
+    fn shadowed() {
+        let err = generate_str(r#"
+            enum lexer {
+                Ident(String): {
+                    "[a-z]+" => Ident(text.into()),
+                }
+                Let: "let",
+            }
+        "#).unwrap_err();
+        assert_eq!(
+            err.0[0].to_string(),
+            r#"regex "let" can never match, it is shadowed by "[a-z]+" (both match "let")"#
+        );
+
+        assert!(generate_str(r#"
+            enum lexer {
+                Ident(String): {
+                    "[a-z]+" => Ident(text.into()),
+                }
+                #[priority = 1]
+                Let: "let",
+            }
+        "#).is_ok());
+    }
+
+    #[test]
+    fn empty_match() {
+        let err = generate_str(r#"
+            enum lexer {
+                LitInt(usize): {
+                    "[0-9]*" => LitInt(text.parse()?),
+                }
+            }
+        "#).unwrap_err();
+        assert_eq!(err.0[0].to_string(), r#"regex "[0-9]*" matches the empty string"#);
+    }
+
+    #[test]
+    fn all_regex_errors() {
+        let err = generate_str(r#"
+            enum lexer {
+                A: "(a",
+                B: "b",
+                C: "[c",
+            }
+        "#).unwrap_err();
+        let errors: Vec<_> = err.0.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec![
+            "regex parse error unexpect end at 2 at (a",
+            "regex parse error unexpect end at 2 at [c",
+        ]);
+    }
+
+    #[test]
+    fn modes() {
+        // the same text in different modes is not shadowed.
+        assert!(generate_str(r#"
+            enum lexer {
+                Quote: "q",
+                mode Str {
+                    Text: "q",
+                }
+            }
+        "#).is_ok());
+
+        let err = generate_str(r#"
+            enum lexer {
+                Quote: "q",
+                mode Str {
+                    Text: "[a-z]",
+                    Other: "t",
+                }
+            }
+        "#).unwrap_err();
+        assert_eq!(
+            err.0[0].to_string(),
+            r#"regex "t" can never match, it is shadowed by "[a-z]" (both match "t")"#
+        );
+    }
+
+    #[test]
+    fn whitespace() {
+        let err = generate_str(r#"
+            #[whitespace = "[ \t]*"]
+            enum lexer {
+                A: "a",
+            }
+        "#).unwrap_err();
+        assert_eq!(err.0[0].to_string(), r#"regex "[ \t]*" matches the empty string"#);
+    }
+}
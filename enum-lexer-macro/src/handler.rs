@@ -0,0 +1,295 @@
+//! the names handlers can use, which all reach into the `TokenIterator`.
+//!
+//! `ctx`, `cursor` and `read_group()` are rewritten into short borrows of
+//! `iterator` instead of being bound before the handler, so a handler can
+//! use them together. like a binding, a name is left alone where a local
+//! variable of the handler shadows it.
+//!
+//! `cursor` keeps `text` up to date. where the handler shadows `text`,
+//! `cursor` is given `token_text` instead, a binding of `handlers` the
+//! handler can't see.
+
+use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+
+/// the names a local variable can shadow.
+const NAMES: &[&str] = &["ctx", "cursor", "text"];
+
+/// the text of the token for `cursor` where `text` is shadowed, hygienic so
+/// that no name of the handler refers to it.
+pub(crate) fn token_text() -> Ident {
+    Ident::new("token_text", Span::mixed_site())
+}
+
+/// rewrite the handler `expr` of the rule with `end_num`.
+pub(crate) fn rewrite(mut expr: syn::Expr, end_num: usize) -> syn::Expr {
+    Rewriter { group_end: end_num + 1, scopes: Vec::new() }.visit_expr_mut(&mut expr);
+    expr
+}
+
+struct Rewriter {
+    // a group is closed by the rule after the one opening it.
+    group_end: usize,
+    // the names of `NAMES` bound by the handler, by scope.
+    scopes: Vec<Vec<Ident>>,
+}
+
+/// the variables bound by a pattern.
+struct Bindings(Vec<Ident>);
+
+impl<'ast> Visit<'ast> for Bindings {
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        if NAMES.iter().any(|name| pat.ident == name) {
+            self.0.push(pat.ident.clone());
+        }
+        visit::visit_pat_ident(self, pat);
+    }
+}
+
+impl Rewriter {
+    /// whether `name` is the one of the lexer, not a local variable.
+    fn is_free(&self, name: &str) -> bool {
+        !self.scopes.iter().flatten().any(|ident| ident == name)
+    }
+
+    /// `func` in a scope, with the variables bound by `pats`.
+    fn scope<'p>(&mut self, pats: impl IntoIterator<Item = &'p syn::Pat>, func: impl FnOnce(&mut Self)) {
+        let mut bindings = Bindings(Vec::new());
+        for pat in pats {
+            bindings.visit_pat(pat);
+        }
+        self.scopes.push(bindings.0);
+        func(self);
+        self.scopes.pop();
+    }
+
+    /// bind the variables of `pat` in the current scope.
+    fn bind(&mut self, pat: &syn::Pat) {
+        let mut bindings = Bindings(Vec::new());
+        bindings.visit_pat(pat);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.extend(bindings.0);
+        }
+    }
+
+    fn is_name(&self, path: &syn::ExprPath, name: &str) -> bool {
+        path.qself.is_none() && path.path.is_ident(name) && self.is_free(name)
+    }
+
+    fn ctx(&self) -> TokenStream {
+        quote! { (&mut iterator.ctx) }
+    }
+
+    fn cursor(&self) -> TokenStream {
+        let text = if self.is_free("text") { format_ident!("text") } else { token_text() };
+        quote! { TokenCursor::new(&mut iterator.cursor, &mut #text, &mut self.span) }
+    }
+
+    fn read_group(&self) -> TokenStream {
+        let group_end = self.group_end;
+        quote! { iterator.next_until(#group_end, self.span) }
+    }
+
+    /// the same rewrite on the tokens of a macro call, e.g. `format!("{}", ctx.depth)`.
+    fn tokens(&self, tokens: TokenStream) -> TokenStream {
+        let mut ret = Vec::<TokenTree>::new();
+        let mut iter = tokens.into_iter().peekable();
+        while let Some(tt) = iter.next() {
+            let after_dot = matches!(ret.last(), Some(TokenTree::Punct(p)) if p.as_char() == '.' || p.as_char() == ':');
+            match tt {
+                TokenTree::Ident(ident) if ident == "ctx" && !after_dot && self.is_free("ctx") => {
+                    ret.extend(self.ctx());
+                }
+                TokenTree::Ident(ident) if ident == "cursor" && !after_dot && self.is_free("cursor") => {
+                    ret.extend(self.cursor());
+                }
+                TokenTree::Ident(ident) if ident == "read_group" && !after_dot && matches!(
+                    iter.peek(),
+                    Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis && g.stream().is_empty()
+                ) => {
+                    iter.next();
+                    ret.extend(self.read_group());
+                }
+                TokenTree::Group(group) => {
+                    let mut new = Group::new(group.delimiter(), self.tokens(group.stream()));
+                    new.set_span(group.span());
+                    ret.push(TokenTree::Group(new));
+                }
+                tt => ret.push(tt),
+            }
+        }
+        ret.into_iter().collect()
+    }
+}
+
+impl VisitMut for Rewriter {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        match expr {
+            syn::Expr::Path(path) if self.is_name(path, "ctx") => {
+                *expr = syn::parse2(self.ctx()).unwrap();
+            }
+            syn::Expr::Path(path) if self.is_name(path, "cursor") => {
+                *expr = syn::parse2(self.cursor()).unwrap();
+            }
+            syn::Expr::Call(call) if call.args.is_empty() && matches!(
+                &*call.func,
+                syn::Expr::Path(path) if path.qself.is_none() && path.path.is_ident("read_group")
+            ) => {
+                *expr = syn::parse2(self.read_group()).unwrap();
+            }
+            syn::Expr::Closure(closure) => {
+                let inputs: Vec<_> = closure.inputs.iter().cloned().collect();
+                self.scope(&inputs, |this| this.visit_expr_mut(&mut closure.body));
+            }
+            syn::Expr::ForLoop(for_loop) => {
+                self.visit_expr_mut(&mut for_loop.expr);
+                let pat = for_loop.pat.clone();
+                self.scope([&pat], |this| this.visit_block_mut(&mut for_loop.body));
+            }
+            syn::Expr::If(syn::ExprIf { cond, then_branch, else_branch, .. }) if matches!(**cond, syn::Expr::Let(_)) => {
+                if let syn::Expr::Let(cond) = &mut **cond {
+                    self.visit_expr_mut(&mut cond.expr);
+                    let pat = cond.pat.clone();
+                    self.scope([&pat], |this| this.visit_block_mut(then_branch));
+                }
+                if let Some((_, else_branch)) = else_branch {
+                    self.visit_expr_mut(else_branch);
+                }
+            }
+            syn::Expr::While(syn::ExprWhile { cond, body, .. }) if matches!(**cond, syn::Expr::Let(_)) => {
+                if let syn::Expr::Let(cond) = &mut **cond {
+                    self.visit_expr_mut(&mut cond.expr);
+                    let pat = cond.pat.clone();
+                    self.scope([&pat], |this| this.visit_block_mut(body));
+                }
+            }
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+
+    fn visit_arm_mut(&mut self, arm: &mut syn::Arm) {
+        let pat = arm.pat.clone();
+        self.scope([&pat], |this| {
+            if let Some((_, guard)) = &mut arm.guard {
+                this.visit_expr_mut(guard);
+            }
+            this.visit_expr_mut(&mut arm.body);
+        });
+    }
+
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        self.scope(None, |this| {
+            for stmt in &mut block.stmts {
+                match stmt {
+                    // the variables of `let` are bound after its value.
+                    syn::Stmt::Local(local) => {
+                        if let Some((_, init)) = &mut local.init {
+                            this.visit_expr_mut(init);
+                        }
+                        this.bind(&local.pat);
+                    }
+                    // an item sees none of the names.
+                    syn::Stmt::Item(_) => {}
+                    stmt => this.visit_stmt_mut(stmt),
+                }
+            }
+        });
+    }
+
+    fn visit_field_value_mut(&mut self, field: &mut syn::FieldValue) {
+        // `S { ctx }` is written `S { ctx: .. }` once rewritten.
+        if matches!(&field.member, syn::Member::Named(name) if name == "ctx" || name == "cursor") {
+            field.colon_token = Some(Default::default());
+        }
+        visit_mut::visit_field_value_mut(self, field);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        mac.tokens = self.tokens(mac.tokens.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rewrite_str(src: &str) -> String {
+        let expr = rewrite(syn::parse_str(src).unwrap(), 2);
+        quote!(#expr).to_string()
+    }
+
+    #[test]
+    fn rewrite_names() {
+        assert_eq!(
+            rewrite_str("{ ctx.depth += 1; let g = read_group()?; ctx.depth -= 1; Group(g) }"),
+            quote!({
+                (&mut iterator.ctx).depth += 1;
+                let g = iterator.next_until(3usize, self.span)?;
+                (&mut iterator.ctx).depth -= 1;
+                Group(g)
+            }).to_string()
+        );
+        assert_eq!(
+            rewrite_str(r#"Ident(intern(ctx, text))"#),
+            quote!(Ident(intern((&mut iterator.ctx), text))).to_string()
+        );
+        assert_eq!(
+            rewrite_str(r#"panic!("{} {:?}", ctx.depth, read_group())"#),
+            quote!(panic!("{} {:?}", (&mut iterator.ctx).depth, iterator.next_until(3usize, self.span))).to_string()
+        );
+        assert_eq!(
+            rewrite_str(r#"{ cursor.eat_while(|c| c != '"'); Str(text) }"#),
+            quote!({
+                TokenCursor::new(&mut iterator.cursor, &mut text, &mut self.span).eat_while(|c| c != '"');
+                Str(text)
+            }).to_string()
+        );
+        // local variables shadow the names.
+        assert_eq!(
+            rewrite_str(r#"{ let n = ctx.n; let ctx = n + 1; f(ctx, |ctx| ctx); ctx }"#),
+            quote!({ let n = (&mut iterator.ctx).n; let ctx = n + 1; f(ctx, |ctx| ctx); ctx }).to_string()
+        );
+        assert_eq!(
+            rewrite_str(r#"{ let Foo { ctx, .. } = foo; format!("{}", ctx) }"#),
+            quote!({ let Foo { ctx, .. } = foo; format!("{}", ctx) }).to_string()
+        );
+        assert_eq!(
+            rewrite_str(r#"(match x { Some(ctx) => ctx, None => ctx }, |x| ctx, if let Some(ctx) = x { ctx } else { ctx })"#),
+            quote!((
+                match x { Some(ctx) => ctx, None => (&mut iterator.ctx) },
+                |x| (&mut iterator.ctx),
+                if let Some(ctx) = x { ctx } else { (&mut iterator.ctx) }
+            )).to_string()
+        );
+        assert_eq!(
+            rewrite_str(r#"{ { let ctx = 1; } S { ctx } }"#),
+            quote!({ { let ctx = 1; } S { ctx: (&mut iterator.ctx) } }).to_string()
+        );
+        assert_eq!(
+            rewrite_str(r#"{ cursor.bump(); let mut cursor = text.chars(); cursor.next(); format!("{:?}", cursor) }"#),
+            quote!({
+                TokenCursor::new(&mut iterator.cursor, &mut text, &mut self.span).bump();
+                let mut cursor = text.chars();
+                cursor.next();
+                format!("{:?}", cursor)
+            }).to_string()
+        );
+        // a shadowed `text` is not the one `cursor` updates.
+        let token_text = token_text();
+        assert_eq!(
+            rewrite_str(r#"{ let text = text.trim(); cursor.bump(); f(text) }"#),
+            quote!({
+                let text = text.trim();
+                TokenCursor::new(&mut iterator.cursor, &mut #token_text, &mut self.span).bump();
+                f(text)
+            }).to_string()
+        );
+        // fields, paths and calls with arguments are left alone.
+        assert_eq!(
+            rewrite_str(r#"f(a.ctx, m::ctx, read_group(1), a.cursor, format!("{}", a.ctx))"#),
+            quote!(f(a.ctx, m::ctx, read_group(1), a.cursor, format!("{}", a.ctx))).to_string()
+        );
+    }
+}
@@ -1,17 +1,14 @@
 
 
-#![feature(proc_macro_quote)]
-
 mod ast;
+mod derive;
 mod gen;
-use syn;
+mod handler;
 use ast::EnumLexer;
 use gen::generate;
 use proc_macro::TokenStream;
-use quote::quote;
 
 // use std::process::Command;
-use proc_macro2::Span;
 
 #[proc_macro]
 pub fn enum_lexer(input: TokenStream) -> TokenStream {
@@ -23,6 +20,13 @@ pub fn enum_lexer_test(input: TokenStream) -> TokenStream {
     enum_lexer_optional_bool(input, true)
 }
 
+/// a lexer of the rules written on the variants of an enum, see the
+/// `Derive` section of the crate docs.
+#[proc_macro_derive(Lexer, attributes(token, regex, group, skip, token_derive, priority, describe, whitespace, lossless, recover, rename, context, lex_error, module))]
+pub fn derive_lexer(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    derive::derive(input).into()
+}
 
 fn enum_lexer_optional_bool(input: TokenStream, test: bool) -> TokenStream {
     let lexer = syn::parse_macro_input!(input as EnumLexer);
@@ -41,11 +45,7 @@ fn enum_lexer_optional_bool(input: TokenStream, test: bool) -> TokenStream {
             stream
         }
         Err(e) => {
-            let what = syn::LitStr::new(format!("{}", e).as_str(), Span::call_site());
-
-            quote!{
-                compile_error!(#what)
-            }
+            e.to_compile_error()
         }
     }.into()
 }
@@ -1,25 +1,33 @@
 use std::cmp;
 use std::fmt;
 use std::cell::RefCell;
-use std::str::Chars;
-use std::iter::Peekable;
+use crate::{LexErrorKind, SpanError};
 
+/// reads the chars of a source, the chars read since the last `get_token`
+/// make the current token.
 #[derive(Debug, Clone)]
 pub struct Cursor<'a> {
+    // the position of `src` in the source map.
     base: u32,
-    token: RefCell<String>,
-    chars: Peekable<Chars<'a>>,
+    src: &'a str,
+    // byte offsets in `src` of the current token, and of the next char.
+    start: usize,
+    pos: usize,
+}
+
+/// a saved position inside the current token, see `Cursor::checkpoint`.
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint {
+    pos: usize,
 }
 
 impl<'a> Iterator for Cursor<'a> {
     type Item = char;
     #[inline(always)]
     fn next(&mut self) -> Option<Self::Item> {
-        let ret = self.chars.next();
-        if let Some(ch) = ret {
-            self.token.borrow_mut().push(ch);
-        }
-        ret
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
     }
 }
 
@@ -31,46 +39,237 @@ impl<'a> Cursor<'a> {
             let base = span.lo;
             Cursor {
                 base,
-                token: RefCell::new(String::new()),
-                chars: src.chars().peekable(),
+                src,
+                start: 0,
+                pos: 0,
             }
         })
     }
 
+    /// the next char, without reading it.
     #[inline(always)]
-    pub fn peek(&mut self) -> Option<&char> {
-        self.chars.peek()
+    pub fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
     }
 
+    /// skip a char, and return the span skipped.
+    ///
+    /// the chars of the current token are skipped too.
     #[inline(always)]
-    pub fn leap(&mut self) {
-        self.chars.next();
+    pub fn leap(&mut self) -> Span {
+        let lo = self.base + self.start as u32;
+        self.next();
+        self.start = self.pos;
+        Span { lo, hi: self.base + self.pos as u32 }
     }
 
+    /// skip chars until `func` holds, and return the span skipped.
     #[inline(always)]
-    pub fn leap_until(&mut self, func: impl Fn(char) -> bool) {
-        while let Some(&ch) = self.chars.peek() {
+    pub fn leap_until(&mut self, func: impl Fn(char) -> bool) -> Span {
+        let lo = self.base + self.start as u32;
+        while let Some(ch) = self.peek() {
             if func(ch) {
                 break;
             } else {
                 self.leap();
             }
         }
+        self.start = self.pos;
+        Span { lo, hi: self.base + self.pos as u32 }
     }
     
+    /// save the current position, so that the chars read after it can be
+    /// given back with `restore`.
+    #[inline(always)]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            pos: self.pos,
+        }
+    }
+
+    /// rewind to `checkpoint`.
+    ///
+    /// the checkpoint must be taken within the current token, i.e. no
+    /// `get_token` in between.
     #[inline(always)]
-    pub fn get_token(&mut self) -> (String, Span) {
-        let lo = self.base as u32;
-        self.base += self.token.borrow().len() as u32;
-        let hi = self.base as u32;
-        (self.token.replace(String::new()), Span{ lo, hi } )
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.pos = checkpoint.pos;
+    }
+
+    /// the text of `span`, a span of this source.
+    pub fn text(&self, span: Span) -> &'a str {
+        &self.src[(span.lo - self.base) as usize..(span.hi - self.base) as usize]
+    }
+
+    /// end the current token, and return its text borrowed from the source.
+    #[inline(always)]
+    pub fn get_token(&mut self) -> (&'a str, Span) {
+        let text = &self.src[self.start..self.pos];
+        let span = Span {
+            lo: self.base + self.start as u32,
+            hi: self.base + self.pos as u32,
+        };
+        self.start = self.pos;
+        (text, span)
+    }
+}
+
+/// the cursor handlers see as `cursor`, to read the rest of a token by hand.
+///
+/// the chars read are added to the token, so `text` and the span of the
+/// token cover them.
+pub struct TokenCursor<'c, 'a, T = &'a str> {
+    cursor: &'c mut Cursor<'a>,
+    text: &'c mut T,
+    span: &'c mut Span,
+}
+
+/// the `text` of handlers: `&'a str` in a lexer with a lifetime, `String`
+/// otherwise.
+pub trait TokenText<'a>: AsRef<str> {
+    /// make the text `text`, the whole token read so far.
+    fn set(&mut self, text: &'a str);
+}
+
+impl<'a> TokenText<'a> for &'a str {
+    fn set(&mut self, text: &'a str) {
+        *self = text;
+    }
+}
+
+impl<'a> TokenText<'a> for String {
+    fn set(&mut self, text: &'a str) {
+        self.clear();
+        self.push_str(text);
     }
 }
 
+impl<'c, 'a, T: TokenText<'a>> TokenCursor<'c, 'a, T> {
+    /// `span` is of the token just read by `cursor`, `text` is set to its text.
+    #[doc(hidden)]
+    pub fn new(cursor: &'c mut Cursor<'a>, text: &'c mut T, span: &'c mut Span) -> Self {
+        text.set(cursor.text(*span));
+        TokenCursor { cursor, text, span }
+    }
 
+    /// the next char, without reading it.
+    #[inline(always)]
+    pub fn peek(&self) -> Option<char> {
+        self.cursor.peek()
+    }
+
+    /// the char `n` chars ahead, `peek_n(0)` is `peek()`.
+    pub fn peek_n(&self, n: usize) -> Option<char> {
+        self.cursor.src[self.cursor.pos..].chars().nth(n)
+    }
+
+    /// whether the text ahead starts with `s`.
+    pub fn starts_with(&self, s: &str) -> bool {
+        self.cursor.src[self.cursor.pos..].starts_with(s)
+    }
+
+    /// read a char into the token.
+    pub fn bump(&mut self) -> Option<char> {
+        let ch = self.cursor.next()?;
+        self.extend();
+        Some(ch)
+    }
+
+    /// read chars into the token while `func` holds, and return them.
+    pub fn eat_while(&mut self, mut func: impl FnMut(char) -> bool) -> &'a str {
+        let lo = self.cursor.pos;
+        while let Some(ch) = self.cursor.peek() {
+            if !func(ch) {
+                break;
+            }
+            self.cursor.next();
+        }
+        self.extend();
+        &self.cursor.src[lo..self.cursor.pos]
+    }
+
+    /// the span of the token so far.
+    pub fn current_span(&self) -> Span {
+        *self.span
+    }
+
+    /// an error of `kind` at the token so far.
+    pub fn error(&self, kind: LexErrorKind) -> SpanError {
+        SpanError::new(kind, *self.span, self.text.as_ref())
+    }
+
+    fn extend(&mut self) {
+        let lo = (self.span.lo - self.cursor.base) as usize;
+        self.text.set(&self.cursor.src[lo..self.cursor.pos]);
+        self.span.hi = self.cursor.base + self.cursor.pos as u32;
+        self.cursor.start = self.cursor.pos;
+    }
+}
+
+/// what the callbacks of `#[derive(Lexer)]` get: the token read, a cursor to
+/// read more of it, and the context of the lexer.
+pub struct Lex<'c, 'a, C> {
+    cursor: TokenCursor<'c, 'a>,
+    ctx: &'c mut C,
+}
+
+impl<'c, 'a, C> Lex<'c, 'a, C> {
+    #[doc(hidden)]
+    pub fn new(cursor: &'c mut Cursor<'a>, text: &'c mut &'a str, span: &'c mut Span, ctx: &'c mut C) -> Self {
+        Lex { cursor: TokenCursor::new(cursor, text, span), ctx }
+    }
+
+    /// the text of the token.
+    pub fn text(&self) -> &'a str {
+        self.cursor.text
+    }
+
+    pub fn span(&self) -> Span {
+        self.cursor.current_span()
+    }
+
+    /// a cursor reading the rest of the token by hand, see `TokenCursor`.
+    pub fn cursor(&mut self) -> &mut TokenCursor<'c, 'a> {
+        &mut self.cursor
+    }
+
+    pub fn ctx(&mut self) -> &mut C {
+        self.ctx
+    }
+
+    /// call `callback` on `self`, so that the type of its argument is known.
+    #[doc(hidden)]
+    pub fn call<T>(&mut self, callback: impl FnOnce(&mut Self) -> T) -> T {
+        callback(self)
+    }
+}
+
+/// what a callback of `#[derive(Lexer)]` may return: `()` on a unit variant,
+/// or a `Result` of the field, its error converted into `E`.
+#[doc(hidden)]
+pub trait Callback<T, E> {
+    fn into_result(self) -> Result<T, E>;
+}
+
+impl<E> Callback<(), E> for () {
+    fn into_result(self) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+impl<T, E, F: Into<E>> Callback<T, E> for Result<T, F> {
+    fn into_result(self) -> Result<T, E> {
+        self.map_err(Into::into)
+    }
+}
+
+/// a place in a source, as given by `Span::start` and `Span::end`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct LineColumn {
+    /// the line, from 1.
     pub line: usize,
+    /// the offset in bytes from the start of the line, from 0. the source is
+    /// not kept, so chars can't be counted.
     pub column: usize,
 }
 
@@ -104,19 +303,17 @@ impl FileInfo {
     }
 }
 
-/// Computes the offsets of each line in the given source string
-/// and the total number of characters
+/// Computes the byte offsets of each line in the given source string
+/// and the total number of bytes
 fn lines_offsets(s: &str) -> (usize, Vec<usize>) {
     let mut lines = vec![0];
-    let mut total = 0;
 
-    for ch in s.chars() {
-        total += 1;
+    for (i, ch) in s.char_indices() {
         if ch == '\n' {
-            lines.push(total);
+            lines.push(i + 1);
         }
     }
-    (total, lines)
+    (s.len(), lines)
 }
 
 
@@ -172,6 +369,19 @@ thread_local! {
     });
 }
 
+/// call `func` with the name of the file of `span`, and the byte offsets of
+/// `span` in its source.
+///
+/// the source itself is not kept, it is only borrowed by the cursors.
+pub(crate) fn with_file<R>(span: Span, func: impl FnOnce(&str, std::ops::Range<usize>) -> R) -> R {
+    SOURCE_MAP.with(|cm| {
+        let cm = cm.borrow();
+        let fi = cm.fileinfo(span);
+        let lo = fi.span.lo as usize;
+        func(&fi.name, span.lo as usize - lo..span.hi as usize - lo)
+    })
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Span {
     pub(crate) lo: u32,
@@ -218,6 +428,15 @@ impl Span {
         })
     }
 
+    /// the byte offsets of the span in its source.
+    pub fn byte_range(&self) -> std::ops::Range<usize> {
+        with_file(*self, |_, range| range)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lo == self.hi
+    }
+
     pub fn first_byte(self) -> Self {
         Span {
             lo: self.lo,
@@ -235,7 +454,7 @@ impl Span {
 
 impl fmt::Debug for Span {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        return write!(f, "bytes({}..{})", self.lo, self.hi);
+        write!(f, "bytes({}..{})", self.lo, self.hi)
     }
 }
 
@@ -0,0 +1,324 @@
+//! rustc-style messages, showing the source lines of spans.
+//!
+//! a `Diagnostic` has a message, labels on spans and notes. it is shown with
+//! `Diagnostic::render`, given the source the spans are in:
+//!
+//! ```text
+//! error: unclosed group
+//!  --> <string>:1:7
+//!   |
+//! 1 | (a (b)
+//!   |       ^ the input ends here
+//!  ::: <string>:1:1
+//!   |
+//! 1 | (a (b)
+//!   | - the group opened here
+//! ```
+//!
+//! the errors of a lexer turn into diagnostics with `Diagnostic::from`, a
+//! parser can make its own with `Diagnostic::error`.
+
+use crate::cursor::with_file;
+use crate::{Expected, LexErrorKind, Span, SpanError};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+            Level::Help => "help",
+        })
+    }
+}
+
+/// a message on a span, the primary labels are underlined with `^`, the
+/// others with `-`.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    /// the lines shown before and after the lines of each label.
+    pub context: usize,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Diagnostic {
+            level,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            context: 0,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Level::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Level::Warning, message)
+    }
+
+    /// add a primary label.
+    pub fn label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into(), primary: true });
+        self
+    }
+
+    /// add a label pointing at a related span.
+    pub fn secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into(), primary: false });
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// show `lines` lines around the lines of each label.
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context = lines;
+        self
+    }
+
+    /// the diagnostic with the lines of `src`, the source its spans are in.
+    ///
+    /// the lexer does not keep its input, so the source is given back here.
+    pub fn render<'d>(&'d self, src: &'d str) -> Rendered<'d> {
+        Rendered { diagnostic: self, src }
+    }
+}
+
+/// a `Diagnostic` shown with its source, see `Diagnostic::render`.
+#[derive(Debug, Clone, Copy)]
+pub struct Rendered<'d> {
+    diagnostic: &'d Diagnostic,
+    src: &'d str,
+}
+
+impl From<&SpanError> for Diagnostic {
+    fn from(e: &SpanError) -> Self {
+        let expected = e.expected();
+        let diagnostic = match &e.kind {
+            LexErrorKind::UnexpectedChar { ch, .. } => {
+                Diagnostic::error(format!("unexpected char {:?}", ch)).label(e.span, "")
+            }
+            LexErrorKind::UnexpectedEof { .. } => {
+                Diagnostic::error("unexpected end of input").label(e.span, "the token is not finished")
+            }
+            LexErrorKind::UnclosedGroup { open } => {
+                Diagnostic::error("unclosed group")
+                    .label(e.span, "the input ends here")
+                    .secondary(*open, "the group opened here")
+            }
+            LexErrorKind::MismatchedGroup { open, expected } => {
+                Diagnostic::error(format!("mismatched closing bracket `{}`", e.text))
+                    .label(e.span, format!("expected `{}`", expected))
+                    .secondary(*open, "the group opened here")
+            }
+            LexErrorKind::UnexpectedClose => {
+                Diagnostic::error(format!("unexpected closing bracket `{}`", e.text)).label(e.span, "no group is open")
+            }
+            LexErrorKind::Handler(err) => Diagnostic::error(err.to_string()).label(e.span, ""),
+        };
+        if expected.is_empty() {
+            diagnostic
+        } else {
+            diagnostic.note(format!("expected {}", Expected(expected)))
+        }
+    }
+}
+
+/// the lines of a label, found in its source.
+struct Snippet {
+    name: String,
+    // the column of the span in bytes, like in `LineColumn`, for the header.
+    column: usize,
+    // (line number, text) of the lines shown.
+    lines: Vec<(usize, String)>,
+    // the lines of the span, and the columns in chars of its first and
+    // last char, to draw the marks under the text.
+    first: usize,
+    last: usize,
+    lo_col: usize,
+    last_col: usize,
+}
+
+impl Snippet {
+    fn new(span: Span, context: usize, src: &str) -> Self {
+        let start = span.start();
+        with_file(span, |name, range| {
+            // without its source, only the place of the span is shown and
+            // there is nothing to draw.
+            if src.get(range.clone()).is_none() {
+                return Snippet {
+                    name: name.to_owned(),
+                    column: start.column,
+                    lines: Vec::new(),
+                    first: start.line,
+                    last: span.end().line,
+                    lo_col: 0,
+                    last_col: 0,
+                };
+            }
+            let line_of = |offset: usize| src[..offset].matches('\n').count();
+            let line_start = |offset: usize| src[..offset].rfind('\n').map_or(0, |i| i + 1);
+            let col = |offset: usize| src[line_start(offset)..offset].chars().count();
+
+            let first = line_of(range.start);
+            // a span ending with a newline ends on the line of the newline.
+            let last_char = if range.end > range.start {
+                src[..range.end].char_indices().next_back().map_or(0, |(i, _)| i)
+            } else {
+                range.end
+            };
+            let last = line_of(last_char);
+
+            let lines = src.split('\n')
+                .enumerate()
+                .skip(first.saturating_sub(context))
+                .take(last + context + 1 - first.saturating_sub(context))
+                .map(|(i, line)| (i + 1, line.trim_end_matches('\r').to_owned()))
+                .collect();
+            Snippet {
+                name: name.to_owned(),
+                column: start.column,
+                lines,
+                first: first + 1,
+                last: last + 1,
+                lo_col: col(range.start),
+                last_col: col(last_char),
+            }
+        })
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, label: &Label, width: usize) -> fmt::Result {
+        let gutter = " ".repeat(width);
+        let arrow = if label.primary { "-->" } else { ":::" };
+        writeln!(f, "{}{} {}:{}:{}", gutter, arrow, self.name, self.first, self.column + 1)?;
+        writeln!(f, "{} |", gutter)?;
+        let mark = if label.primary { '^' } else { '-' };
+        let message = if label.message.is_empty() { String::new() } else { format!(" {}", label.message) };
+
+        if self.first == self.last {
+            for (n, text) in &self.lines {
+                writeln!(f, "{:>w$} | {}", n, text, w = width)?;
+                if *n == self.first {
+                    // tabs are kept, so the marks line up with the text.
+                    let pad: String = text.chars().take(self.lo_col)
+                        .map(|c| if c == '\t' { '\t' } else { ' ' })
+                        .collect();
+                    let marks = mark.to_string().repeat(self.last_col + 1 - self.lo_col);
+                    writeln!(f, "{} | {}{}{}", gutter, pad, marks, message)?;
+                }
+            }
+            return Ok(());
+        }
+
+        // a span of several lines is drawn on its left.
+        for (n, text) in &self.lines {
+            let side = if *n > self.first && *n <= self.last { "| " } else { "  " };
+            writeln!(f, "{:>w$} | {}{}", n, side, text, w = width)?;
+            if *n == self.first {
+                writeln!(f, "{} |  {}{}", gutter, "_".repeat(self.lo_col + 1), mark)?;
+            } else if *n == self.last {
+                writeln!(f, "{} | |{}{}{}", gutter, "_".repeat(self.last_col + 1), mark, message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Rendered { diagnostic: d, src } = *self;
+        writeln!(f, "{}: {}", d.level, d.message)?;
+        let snippets: Vec<_> = d.labels.iter().map(|l| Snippet::new(l.span, d.context, src)).collect();
+        let width = snippets.iter()
+            .filter_map(|s| s.lines.last())
+            .map(|(n, _)| n.to_string().len())
+            .max()
+            .unwrap_or(1);
+        for (snippet, label) in snippets.iter().zip(&d.labels) {
+            snippet.render(f, label, width)?;
+        }
+        for note in &d.notes {
+            writeln!(f, "{} = note: {}", " ".repeat(width), note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Cursor;
+
+    /// the span of `range` in a new file of `src`.
+    fn span(src: &'static str, range: std::ops::Range<usize>) -> Span {
+        let base = Cursor::new_file("test.txt", src).get_token().1.lo;
+        Span { lo: base + range.start as u32, hi: base + range.end as u32 }
+    }
+
+    #[test]
+    fn render() {
+        let src = "let a = 1;\nlet\tb = (2\n  + 3;\n";
+        let d = Diagnostic::error("unclosed group")
+            .label(span(src, 26..26), "here")
+            .secondary(span(src, 19..20), "opened")
+            .note("close it");
+        assert_eq!(d.render(src).to_string(), "\
+error: unclosed group
+ --> test.txt:3:5
+  |
+3 |   + 3;
+  |     ^ here
+ ::: test.txt:2:9
+  |
+2 | let\tb = (2
+  |    \t    - opened
+  = note: close it
+");
+
+        // the newline ending a span is not shown.
+        let d = Diagnostic::error("line").label(span(src, 4..11), "");
+        assert!(d.render(src).to_string().ends_with("1 | let a = 1;\n  |     ^^^^^^^\n"), "{}", d.render(src));
+
+        let d = Diagnostic::warning("long").label(span(src, 8..21), "").context(1);
+        assert_eq!(d.render(src).to_string(), "\
+warning: long
+ --> test.txt:1:9
+  |
+1 |   let a = 1;
+  |  _________^
+2 | | let\tb = (2
+  | |__________^
+3 |     + 3;
+");
+
+        // a source that is not the one of the span shows no lines.
+        let d = Diagnostic::error("other").label(span(src, 19..20), "");
+        assert_eq!(d.render("").to_string(), "error: other\n --> test.txt:2:9\n  |\n");
+    }
+}
@@ -5,13 +5,13 @@
 //! ## Write a lexer
 //! 
 //! ```no_run
-//! #![feature(exclusive_range_pattern)]
-//!
 //! use enum_lexer::enum_lexer;
-//!
+//! 
 //! enum_lexer! {
 //!     #[derive(Debug, Eq, PartialEq)]
 //!     enum lexer {
+//!         Def: r"def",                            // keywords go before `Ident`, the first
+//!         Let: r"let",                            // declared rule wins on the same text.
 //!         Ident(String): {
 //!             r"[A-Za-z_][A-Za-z_0-9]*" => Ident(text),
 //!         }
@@ -23,14 +23,7 @@
 //!             r"\+" => Op('+'),
 //!             r"\-" => Op('-'),
 //!         }
-//!         Def: r"def",
-//!         Let: r"let",
-//!         Group(Vec<Token>, char) : {
-//!             r"\(" => {
-//!                 Group(read_group()?, '(')       // construct a token tree within '(', ')'.
-//!             }
-//!             r"\)" => { panic!("error") }
-//!         }
+//!         Group: ("(", ")"), ("[", "]"),          // token trees within brackets.
 //!         COMMENTS: {                             // COMMENTS will be ignored
 //!             r"//.*?\n" => !,
 //!             r"/\*.*?\*/" => !,
@@ -51,12 +44,12 @@
 //!      
 //!      #[derive(Debug, Eq, PartialEq)]
 //!      pub enum TokenInner {
+//!          Def,
+//!          Let,
 //!          Ident(String),
 //!          LitInt(usize),
 //!          Op(char),
-//!          Def,
-//!          Let,
-//!          Group(Vec<Token>, char),
+//!          Group(enum_lexer::Group<Token>),
 //!      }
 //!      pub struct TokenIterator{...}
 //!      pub type LexError = Box<&dyn Error>;
@@ -74,6 +67,407 @@
 //! println!("{:?}", vec);
 //! ```
 //! 
+//! ## Token Text
+//! 
+//! Handlers get the text of the token as `text: String`. Declare a lifetime on
+//! the lexer to get `text: &'a str` instead, borrowed from the input, and keep
+//! the text in tokens without a copy. `Token` and `TokenInner` then take the
+//! lifetime too.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     enum lexer<'a> {
+//!         Ident(&'a str): {
+//!             r"\w+" => Ident(text),
+//!         }
+//!     }
+//! }
+//! ```
+//! 
+//! ## Token Kinds
+//! 
+//! `TokenKind` has the variants of `TokenInner` without their fields, and is
+//! `Copy`, `Eq` and `Hash`, to ask what a token is or to keep sets of expected
+//! tokens. `TokenInner::kind()` gives the kind of a token, `TokenKind::name()` the
+//! name of the variant, and `TokenKind::ALL` lists every kind.
+//! 
+//! ```ignore
+//! use lexer::TokenKind;
+//! 
+//! let token = lexer::parse_str("let").unwrap().next().unwrap().unwrap();
+//! assert_eq!(token.kind(), TokenKind::Let);
+//! assert_eq!(TokenKind::Let.name(), "Let");
+//! ```
+//! 
+//! `TokenKind::describe()` tells what a kind is in messages like "expected X":
+//! the `#[describe = ".."]` of the variant, its doc comment, or for a variant
+//! with a single rule matching a plain text, the text in backquotes, e.g.
+//! `` `let` ``. Other variants are described by their name. `TokenKind` and
+//! `Token` show their description with `Display`.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     enum lexer {
+//!         Let: r"let",
+//!         #[describe = "integer literal"]
+//!         LitInt(usize): {
+//!             r"\d+" => LitInt(text.parse()?),
+//!         }
+//!     }
+//! }
+//! 
+//! assert_eq!(lexer::TokenKind::Let.describe(), "`let`");
+//! assert_eq!(format!("expected {}", lexer::TokenKind::LitInt), "expected integer literal");
+//! ```
+//! 
+//! ## Rule Priority
+//! 
+//! When several rules match the same longest text, the one declared first wins.
+//! `#[priority = N]` on a variant or on a single rule overrides this, higher
+//! priorities win (the default is `0`). A rule that loses on every input it
+//! matches can never fire and is reported as a compile error.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     enum lexer {
+//!         Ident(String): {
+//!             r"[A-Za-z_][A-Za-z_0-9]*" => Ident(text),
+//!         }
+//!         #[priority = 1]
+//!         Let: r"let",
+//!     }
+//! }
+//! ```
+//! 
+//! ## Groups
+//! 
+//! A variant declared with pairs of brackets, `Group: ("(", ")"), ("[", "]")`,
+//! reads the tokens between an opening bracket and its closing bracket into an
+//! `enum_lexer::Group`: the `delimiters`, the spans of the `open` and the `close`
+//! bracket and the `tokens` inside, groups nested in it included. The brackets are
+//! texts, not regexes. The span of the token covers both brackets.
+//! 
+//! A closing bracket of another pair is a `MismatchedGroup` error, one outside of
+//! any group an `UnexpectedClose` error, and the end of the input inside a group an
+//! `UnclosedGroup` error. Nested groups are read in a loop rather than by
+//! recursion.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     #[derive(Debug, PartialEq)]
+//!     enum lexer<'a> {
+//!         Word(&'a str): {
+//!             r"\w+" => Word(text),
+//!         }
+//!         Group: ("(", ")"), ("[", "]"), ("{", "}"),
+//!     }
+//! }
+//! 
+//! let token = lexer::parse_str("(a [b])").unwrap().next().unwrap().unwrap();
+//! match token.inner {
+//!     lexer::TokenInner::Group(group) => assert_eq!(group.tokens.len(), 2),
+//!     _ => unreachable!(),
+//! }
+//! ```
+//! 
+//! Handlers can still read a group by hand with `read_group()`, which reads tokens
+//! until the rule declared right after the one of the handler.
+//! 
+//! ## Modes
+//! 
+//! Rules declared in `mode Name { ... }` are only tried while the `TokenIterator`
+//! is in mode `Name`, the other rules belong to `Mode::Initial`. Handlers switch
+//! modes with `iterator.push_mode(..)`, `iterator.pop_mode()` and
+//! `iterator.set_mode(..)`. A variant can be declared again in another mode.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     enum lexer {
+//!         Quote: {
+//!             "\"" => { iterator.push_mode(Mode::Str); Quote }
+//!         }
+//!         #[whitespace = false]
+//!         mode Str {
+//!             Text(String): {
+//!                 r#"[^"]+"# => Text(text),
+//!             }
+//!             Quote: {
+//!                 "\"" => { iterator.pop_mode(); Quote }
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//! 
+//! ## Whitespace
+//! 
+//! Chars of `char::is_whitespace` are skipped before each token. `#[whitespace = false]`
+//! on the lexer or on a mode turns this off, and `#[whitespace = r"regex"]` skips
+//! the text of an ordinary (ignored) rule instead. A mode without the attribute
+//! skips the same as the lexer.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     #[whitespace = r"[ \t]+"]                 // newlines are tokens
+//!     enum lexer {
+//!         Newline: r"\n",
+//!         Word(String): {
+//!             r"\w+" => Word(text),
+//!         }
+//!     }
+//! }
+//! ```
+//! 
+//! ## Lossless Lexing
+//! 
+//! With `#[lossless]` on the lexer, each `Token` keeps the whitespace and the
+//! text of ignored rules around it as `Trivia` (a `TriviaKind` and a `Span`):
+//! `leading` holds the trivia before the token, `trailing` the trivia after it up
+//! to the end of its line. Writing out the leading trivia, the token and the
+//! trailing trivia of every token (tokens of a `read_group` included) gives back
+//! the input, `Span::byte_range` slices the source. The next token is read ahead
+//! to find the trailing trivia. The token of a bracket group keeps the trivia
+//! around its brackets, the tokens inside keep their own.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     #[lossless]
+//!     enum lexer {
+//!         Ident(String): {
+//!             r"\w+" => Ident(text),
+//!         }
+//!         COMMENTS: {
+//!             r"//[^\n]*" => !,                    // kept as `TriviaKind::Comment`
+//!         }
+//!     }
+//! }
+//! ```
+//! 
+//! ## Scanning by Hand
+//! 
+//! Tokens that no regex can describe, like nested comments or raw strings, can be
+//! started by a regex and finished in the handler with `cursor`. It has `peek`,
+//! `peek_n`, `starts_with`, `bump`, `eat_while` and `current_span`. The chars it
+//! reads become part of the token, in `text` and in the token's span. A local
+//! variable named `cursor` shadows it.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     enum lexer<'a> {
+//!         Comment(&'a str): {
+//!             r"/\*" => {
+//!                 let mut depth = 1;
+//!                 while depth > 0 {
+//!                     if cursor.starts_with("/*") {
+//!                         depth += 1;
+//!                     } else if cursor.starts_with("*/") {
+//!                         depth -= 1;
+//!                     } else if cursor.bump().is_none() {
+//!                         return Err(cursor.error(LexErrorKind::UnexpectedEof { expected: &[] }).into());
+//!                     } else {
+//!                         continue;
+//!                     }
+//!                     cursor.bump();
+//!                     cursor.bump();
+//!                 }
+//!                 Comment(text)
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//! 
+//! ## Error Recovery
+//! 
+//! By default the iterator yields an error at the first text no rule matches.
+//! With `#[recover]` that text becomes an `Error` token instead, and lexing goes
+//! on to the end of the input. The token runs from the unknown char to the next
+//! char a token can start with. It holds the text as `Error(&'a str)` in a lexer
+//! with a lifetime, and as `Error(String)` otherwise. The variant is glob imported
+//! in handlers, so if the enclosing module has another `Error`, e.g. from `use
+//! std::error::Error`, rename it with `#[rename(Error = Unknown)]`.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     #[recover]
+//!     enum lexer<'a> {
+//!         Ident(&'a str): {
+//!             r"\w+" => Ident(text),
+//!         }
+//!     }
+//! }
+//! 
+//! // Ident("a"), Error("?!"), Ident("b")
+//! let tokens = lexer::parse_str("a ?! b").unwrap();
+//! ```
+//! 
+//! ## Handler Context
+//! 
+//! `type Context = T;` gives handlers a `ctx: &mut T`, to intern names, count
+//! nesting or collect warnings while lexing. Start with
+//! `parse_str_with_context(src, ctx)` and take the context back with
+//! `TokenIterator::into_ctx`. Without a context declared, `ctx` is `()`. A local
+//! variable named `ctx` in a handler shadows it, like any other binding.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     type Context = Vec<String>;
+//!     enum lexer {
+//!         Ident(usize): {
+//!             r"\w+" => {
+//!                 ctx.push(text);
+//!                 Ident(ctx.len() - 1)
+//!             }
+//!         }
+//!     }
+//! }
+//! 
+//! let mut iter = lexer::parse_str_with_context("a b", Vec::new()).unwrap();
+//! let tokens: lexer::Result<Vec<_>> = (&mut iter).collect();
+//! let names = iter.into_ctx();
+//! ```
+//! 
+//! ## Visibility and Names
+//! 
+//! The visibility of the enum is given to the generated module, so `pub enum
+//! lexer` can be used from other modules and crates. `#[rename(..)]` renames the
+//! items in the module, to tell apart the items of several lexers once they are
+//! re-exported. It can rename `Token`, `TokenInner`, `TokenIterator`,
+//! `TokenKind`, `Lexer`, `parse_str` and the `Error` variant of `#[recover]`.
+//! Renaming `parse_str` also renames `parse_str_with_name` and the other
+//! `parse_str_with_*` functions.
+//! 
+//! ```ignore
+//! enum_lexer! {
+//!     #[rename(Token = Word, TokenInner = WordKind, parse_str = lex)]
+//!     pub enum words {
+//!         Word: r"\w+",
+//!     }
+//! }
+//! 
+//! pub use words::{Word, WordKind};
+//! let tokens = words::lex("a b").unwrap();
+//! ```
+//! 
+//! ## The Lexer Trait
+//! 
+//! The module of each lexer has a `Lexer` type implementing `enum_lexer::Lexer`,
+//! so that test harnesses, token dumpers and parsers can be generic over lexers.
+//! `Lexer::lex(src)` and `Lexer::lex_named(name, src)` need a `Context` with a
+//! `Default`, `Lexer::lex_with_context(name, src, ctx)` takes any context.
+//! 
+//! ```ignore
+//! fn count<L: enum_lexer::Lexer>(src: &str) -> Result<usize, L::Error>
+//! where
+//!     L::Context: Default,
+//! {
+//!     let mut n = 0;
+//!     for token in L::lex(src)? {
+//!         token?;
+//!         n += 1;
+//!     }
+//!     Ok(n)
+//! }
+//! 
+//! let n = count::<lexer::Lexer>("let a = 1").unwrap();
+//! ```
+//! 
+//! ## Derive
+//! 
+//! `#[derive(Lexer)]` reads the rules from the attributes of an ordinary enum,
+//! which rustfmt and rust-analyzer handle like any other enum. The enum is the
+//! `TokenInner` of the lexer, the other items go to a module named after the enum
+//! in snake case (`#[module(name)]` picks another name).
+//! The module, its `Token`, `TokenIterator` and `Lexer` are as visible as the enum.
+//! 
+//! - `#[token("let")]` matches the text as is, `#[regex(r"[0-9]+")]` a regex.
+//!   A variant can have several of them, and `#[priority = N]`.
+//! - a callback after the rule, `#[regex(r"..", |lex| ..)]`, gets a `Lex` with
+//!   the `text()` and `span()` of the token, its `cursor()` and the `ctx()`. It
+//!   returns `()` on a unit variant, and a `Result` of the field otherwise.
+//! - without a callback, the field is `text.into()`.
+//! - `#[group("(", ")")]`, possibly repeated, makes a group variant, whose
+//!   field is an `enum_lexer::Group` of the `Token` of the module.
+//! - `#[skip(r"..")]` on the enum skips what the regex matches, like comments.
+//! - `#[describe = ".."]` or a doc comment describes a variant, see `TokenKind`.
+//! - `#[whitespace = ..]`, `#[lossless]`, `#[recover]` and `#[rename(..)]` work as
+//!   above, with `#[recover]` the enum declares the `Error` variant itself.
+//! - `#[context(T)]` and `#[lex_error(T)]` set `Context` and `LexError`.
+//! - a derive does not see the other derives of the enum, the ones of `Token` are
+//!   given with `#[token_derive(..)]`.
+//! 
+//! ```ignore
+//! #[derive(Debug, PartialEq, Lexer)]
+//! #[token_derive(Debug)]
+//! #[skip(r"//[^\n]*")]
+//! pub enum Tok<'a> {
+//!     #[token("let")]
+//!     Let,
+//!     #[regex(r"[0-9]+", |lex| lex.text().parse())]
+//!     Int(u64),
+//!     #[regex(r"\w+")]
+//!     Ident(&'a str),
+//! }
+//! 
+//! let tokens = tok::parse_str("let x // y").unwrap();
+//! ```
+//! 
+//! ## Errors
+//! 
+//! Errors of the lexer are `SpanError`s: a `LexErrorKind`, the `span` where it
+//! happened and its `text`. The kinds are
+//! 
+//! - `UnexpectedChar { ch, expected }`: no rule can go on with `ch`. `expected`
+//!   holds the chars that could.
+//! - `UnexpectedEof { expected }`: the input ended inside a token.
+//! - `UnclosedGroup { open }`: the input ended inside the group opened at `open`.
+//! - `MismatchedGroup { open, expected }`: a closing bracket of another group
+//!   than the one opened at `open`, which `expected` closes. The group stays
+//!   open.
+//! - `UnexpectedClose`: a closing bracket outside of any group.
+//! - `Handler(error)`: an error of a handler. With the default `LexError`, the
+//!   error of a `?` in a handler is wrapped in it, with the span and the text of
+//!   the token. A custom `LexError` gets the error of `?` as it is, without a
+//!   span, `cursor.error(LexErrorKind::Handler(error.into()))` adds one.
+//! 
+//! The unexpected char is a part of the error, so the iterator can go on past it.
+//! With the default `LexError` of `Box<dyn Error>`, use `downcast_ref::<SpanError>()`.
+//! A custom `LexError` must implement `From<SpanError>`.
+//! 
+//! ## Diagnostics
+//! 
+//! `enum_lexer::diagnostic` shows errors with the source lines of their spans, in
+//! the style of rustc. A lexer error turns into a `Diagnostic` with
+//! `Diagnostic::from(&error)`. A parser can build its own. The lexer does not keep
+//! a copy of its input, so `render(&src)` is given the source to show:
+//! 
+//! ```ignore
+//! use enum_lexer::diagnostic::Diagnostic;
+//! 
+//! let diagnostic = Diagnostic::error("mismatched brackets")
+//!     .label(close.span, "this `]`")
+//!     .secondary(open.span, "does not close this `(`")
+//!     .note("brackets must be closed in order")
+//!     .context(1);
+//! eprint!("{}", diagnostic.render(&src));
+//! ```
+//! 
+//! ```text
+//! error: mismatched brackets
+//!  --> main.txt:2:7
+//!   |
+//! 2 | f(a, b]
+//!   |       ^ this `]`
+//!  ::: main.txt:2:2
+//!   |
+//! 2 | f(a, b]
+//!   |  - does not close this `(`
+//!   = note: brackets must be closed in order
+//! ```
+//!
+//! The column of `file:line:column`, like the one of `Span::start()`, counts
+//! bytes from the start of the line.
+//! 
 //! ## Customizing Error Types
 //! 
 //! ```ignore
@@ -87,41 +481,196 @@
 //! 
 
 mod cursor;
+pub mod diagnostic;
 
 pub use enum_lexer_macro::enum_lexer;
 
 pub use enum_lexer_macro::enum_lexer_test;
 
+pub use enum_lexer_macro::Lexer;
+
 pub use cursor::*;
 
 use std::{ fmt };
+use std::ops::RangeInclusive;
+
+/// a lexer made by `enum_lexer!` or `#[derive(Lexer)]`, for code generic over
+/// lexers.
+///
+/// the module of each lexer has a `Lexer` type implementing it.
+pub trait Lexer {
+    type Token<'a>;
+    type Error;
+    /// what handlers see as `ctx`.
+    type Context;
+    type Iter<'a>: Iterator<Item = Result<Self::Token<'a>, Self::Error>>;
+
+    /// lex `src`, the source named `name`, with the context `ctx`.
+    fn lex_with_context<'a>(name: &str, src: &'a str, ctx: Self::Context) -> Result<Self::Iter<'a>, Self::Error>;
 
-#[derive(Debug, Clone)]
-pub struct SpanError(pub Span);
+    fn lex(src: &str) -> Result<Self::Iter<'_>, Self::Error>
+    where
+        Self::Context: Default,
+    {
+        Self::lex_named("<string>", src)
+    }
+
+    /// lex `src`, the source named `name` in spans.
+    fn lex_named<'a>(name: &str, src: &'a str) -> Result<Self::Iter<'a>, Self::Error>
+    where
+        Self::Context: Default,
+    {
+        Self::lex_with_context(name, src, Default::default())
+    }
+}
+
+/// what went wrong while lexing, see `SpanError`.
+#[derive(Debug)]
+pub enum LexErrorKind {
+    /// a char no rule can go on with, the chars that could are `expected`.
+    UnexpectedChar {
+        ch: char,
+        expected: &'static [RangeInclusive<char>],
+    },
+    /// the input ended inside a token.
+    UnexpectedEof {
+        expected: &'static [RangeInclusive<char>],
+    },
+    /// the input ended inside the group opened at `open`.
+    UnclosedGroup {
+        open: Span,
+    },
+    /// a closing bracket of another group than the one opened at `open`,
+    /// which `expected` closes.
+    MismatchedGroup {
+        open: Span,
+        expected: &'static str,
+    },
+    /// a closing bracket outside of any group.
+    UnexpectedClose,
+    /// an error of a handler.
+    Handler(Box<dyn std::error::Error>),
+}
+
+/// an error at `span`, whose text is `text`.
+#[derive(Debug)]
+pub struct SpanError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+    pub text: String,
+}
+
+impl SpanError {
+    pub fn new(kind: LexErrorKind, span: Span, text: impl Into<String>) -> Self {
+        SpanError { kind, span, text: text.into() }
+    }
+
+    /// the chars that could have been read instead, empty if not known.
+    pub fn expected(&self) -> &'static [RangeInclusive<char>] {
+        match self.kind {
+            LexErrorKind::UnexpectedChar { expected, .. } => expected,
+            LexErrorKind::UnexpectedEof { expected } => expected,
+            _ => &[],
+        }
+    }
+}
 
 impl std::error::Error for SpanError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+        match &self.kind {
+            LexErrorKind::Handler(e) => Some(e.as_ref()),
+            _ => None,
+        }
     }
 }
 
-impl std::fmt::Display for SpanError {
+impl fmt::Display for SpanError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "lexer error at {:?}", self.0)
+        let start = self.span.start();
+        write!(f, "{}:{}:{}: {}", self.span.source_name(), start.line, start.column + 1, self.kind)
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct GroupError();
-
-impl std::error::Error for GroupError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        None
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expected = match self {
+            LexErrorKind::UnexpectedChar { ch, expected } => {
+                write!(f, "unexpected char {:?}", ch)?;
+                expected
+            }
+            LexErrorKind::UnexpectedEof { expected } => {
+                write!(f, "unexpected end of input")?;
+                expected
+            }
+            LexErrorKind::UnclosedGroup { .. } => {
+                return write!(f, "unclosed group");
+            }
+            LexErrorKind::MismatchedGroup { expected, .. } => {
+                return write!(f, "mismatched closing bracket, expected `{}`", expected);
+            }
+            LexErrorKind::UnexpectedClose => {
+                return write!(f, "unexpected closing bracket");
+            }
+            LexErrorKind::Handler(e) => {
+                return write!(f, "{}", e);
+            }
+        };
+        if !expected.is_empty() {
+            write!(f, ", expected {}", Expected(expected))?;
+        }
+        Ok(())
     }
 }
 
-impl std::fmt::Display for GroupError {
+/// shows a list of expected chars.
+pub(crate) struct Expected(pub(crate) &'static [RangeInclusive<char>]);
+
+impl fmt::Display for Expected {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "brace not match")
+        // long lists, e.g. of unicode classes, are cut short.
+        const SHOWN: usize = 8;
+        for (i, range) in self.0.iter().take(SHOWN).enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            if range.start() == range.end() {
+                write!(f, "{:?}", range.start())?;
+            } else {
+                write!(f, "{:?}..={:?}", range.start(), range.end())?;
+            }
+        }
+        if self.0.len() > SHOWN {
+            write!(f, " or {} more", self.0.len() - SHOWN)?;
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// the tokens between a pair of brackets, read by a group variant like
+/// `Group: ("(", ")"), ("[", "]")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group<T> {
+    /// the brackets of the group, e.g. `("(", ")")`.
+    pub delimiters: (&'static str, &'static str),
+    /// the spans of the opening and the closing bracket.
+    pub open: Span,
+    pub close: Span,
+    pub tokens: Vec<T>,
+}
+
+/// text between tokens, kept on each `Token` by lexers with `#[lossless]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// skipped whitespace.
+    Whitespace,
+    /// text of an ignored rule, e.g. in `COMMENTS`.
+    Comment,
+    /// the token closing a `read_group`, which is not a token of its own.
+    GroupClose,
+}
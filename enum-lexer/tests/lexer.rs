@@ -1,7 +1,5 @@
-#![feature(exclusive_range_pattern)]
-
 use enum_lexer::{
-    enum_lexer
+    enum_lexer, Lexer
 };
 
 
@@ -9,16 +7,19 @@ use enum_lexer::{
 enum_lexer! {
     #[derive(Debug, Eq, PartialEq)]
     enum lexer {
+        Def: r"def",
         Ident(String) : {
-            r"[A-Za-z_][A-Za-z_0-9]*" => Ident(text),
+            r"[\p{XID_Start}_]\p{XID_Continue}*" => Ident(text),
         }
         LitStr(String) : {
             "\".*?\"" => LitStr(text),
         }
+        #[describe = "integer literal"]
         LitInt(usize) : {
-            r"[0-9][0-9]*" => LitInt(text.parse::<usize>()?),
+            r"\d+" => LitInt(text.parse::<usize>()?),
         } 
-        Def: r"def",
+        Arrow: r"\-\->",
+        #[priority = 1]
         Let: r"let",
         Op(char) : {
             r"\+" => Op('+'),
@@ -26,15 +27,7 @@ enum_lexer! {
             r"=" => Op('='),
             r"!" => Op('!'),
         }
-        Group(Vec<TokenInner>, char) : {
-            r"\(" => {
-                Group(
-                    read_group()?.into_iter().map(|t| t.inner).collect(),
-                    '('
-                )
-            }
-            r"\)" => { panic!("error") }
-        }
+        Group: ("(", ")"),
         COMMENTS: {
             r"//.*?\n" => !,
             r"/\*.*?\*/" => !,
@@ -42,6 +35,358 @@ enum_lexer! {
     }
 }
 
+enum_lexer! {
+    #[derive(Debug, Eq, PartialEq)]
+    enum interp<'a> {
+        Ident(&'a str): {
+            r"[a-z]+" => Ident(text),
+        }
+        Quote: {
+            "\"" => {
+                iterator.push_mode(Mode::Str);
+                Quote
+            }
+        }
+        RBrace: {
+            r"\}" => {
+                iterator.pop_mode();
+                RBrace
+            }
+        }
+        #[whitespace = false]
+        mode Str {
+            Text(&'a str): {
+                r#"[^"$]+"# => Text(text),
+            }
+            Quote: {
+                "\"" => {
+                    iterator.pop_mode();
+                    Quote
+                }
+            }
+            Interp: {
+                r"\$\{" => {
+                    iterator.push_mode(Mode::Initial);
+                    Interp
+                }
+            }
+        }
+    }
+}
+
+enum_lexer! {
+    #[whitespace = r"[ \t]+"]
+    #[derive(Debug, Eq, PartialEq)]
+    enum lines<'a> {
+        Newline: r"\n",
+        Word(&'a str): {
+            r"\w+" => Word(text),
+        }
+    }
+}
+
+enum_lexer! {
+    #[lossless]
+    #[recover]
+    #[derive(Debug, Eq, PartialEq)]
+    enum trivia {
+        Ident(String): {
+            r"\w+" => Ident(text),
+        }
+        Group: ("(", ")"),
+        COMMENTS: {
+            r"//[^\n]*" => !,
+        }
+    }
+}
+
+enum_lexer! {
+    #[recover]
+    #[derive(Debug, Eq, PartialEq)]
+    enum recovering<'a> {
+        Ident(&'a str): {
+            r"[a-z]+" => Ident(text),
+        }
+        LitStr(&'a str): {
+            "\"[^\"\n]*\"" => LitStr(text),
+        }
+        Plus: r"\+",
+    }
+}
+
+mod words {
+    use enum_lexer::enum_lexer;
+
+    enum_lexer! {
+        #[rename(Token = Word, TokenInner = WordKind, TokenIterator = Words, parse_str = lex)]
+        #[derive(Debug, Eq, PartialEq)]
+        pub enum lexer<'a> {
+            Word(&'a str): {
+                r"\w+" => Word(text),
+            }
+            Pair: ("(", ")"),
+        }
+    }
+}
+
+// variants named like the items of the module.
+enum_lexer! {
+    #[derive(Debug, Eq, PartialEq)]
+    enum items {
+        Lexer: "lexer",
+        Mode: "mode",
+    }
+}
+
+mod unknown {
+    // the `Error` token of `#[recover]` next to another `Error`.
+    use std::error::Error;
+    use enum_lexer::enum_lexer;
+
+    /// the errors of `plain` on `src`, none as it reads any text.
+    pub fn errors(src: &str) -> Vec<Box<dyn Error>> {
+        plain::parse_str(src).unwrap().filter_map(Result::err).collect()
+    }
+
+    enum_lexer! {
+        #[recover]
+        pub enum plain {
+            Word: r"\w+",
+        }
+    }
+
+    enum_lexer! {
+        #[recover]
+        #[rename(Error = Unknown)]
+        #[derive(Debug, Eq, PartialEq)]
+        pub enum lexer<'a> {
+            Word(&'a str): {
+                r"\w+" => Word(text),
+            }
+            Quoted(&'a str): {
+                r"'\w+'" => {
+                    if text.len() > 10 {
+                        Unknown(text)
+                    } else {
+                        Quoted(text)
+                    }
+                }
+            }
+        }
+    }
+}
+
+enum_lexer! {
+    #[derive(Debug, Clone, PartialEq)]
+    #[lossless]
+    enum brackets<'a> {
+        #[priority = 1]
+        Block: ("begin", "end"),
+        Word(&'a str): {
+            r"\w+" => Word(text),
+        }
+        Group: ("(", ")"), ("[", "]"), ("{", "}"),
+        COMMENTS: {
+            r"#[^\n]*" => !,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Symbols {
+    names: Vec<String>,
+    depth: usize,
+    max_depth: usize,
+}
+
+fn intern(symbols: &mut Symbols, name: &str) -> usize {
+    match symbols.names.iter().position(|n| n == name) {
+        Some(i) => i,
+        None => {
+            symbols.names.push(name.into());
+            symbols.names.len() - 1
+        }
+    }
+}
+
+enum_lexer! {
+    type Context = Symbols;
+    #[derive(Debug, Eq, PartialEq)]
+    enum symbols {
+        Ident(usize): {
+            r"\w+" => Ident(intern(ctx, &text)),
+        }
+        Hash(usize): {
+            r"#\w+" => {
+                // a local `ctx` shadows the context of the lexer.
+                let ctx = &text[1..];
+                Hash(ctx.len())
+            }
+        }
+        Group: ("(", ")"),
+    }
+}
+
+// a group read by its handler with `read_group()`, the rule after the one
+// opening it closes it.
+enum_lexer! {
+    type Context = Symbols;
+    #[derive(Debug, Eq, PartialEq)]
+    enum nested {
+        Ident(usize): {
+            r"\w+" => Ident(intern(ctx, &text)),
+        }
+        Group(Vec<Token>): {
+            r"\(" => {
+                ctx.depth += 1;
+                ctx.max_depth = ctx.max_depth.max(ctx.depth);
+                let group = read_group()?;
+                ctx.depth -= 1;
+                Group(group)
+            }
+            r"\)" => { panic!("unmatched at depth {}", ctx.depth) }
+        }
+    }
+}
+
+enum_lexer! {
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    enum scan<'a> {
+        Ident(&'a str): {
+            r"\w+" => Ident(text),
+        }
+        Tildes(usize): {
+            r"~" => {
+                // a local `text` shadows the text of the token.
+                let text = text.len();
+                let rest = cursor.eat_while(|c| c == '~');
+                Tildes(text + rest.len())
+            }
+        }
+        RawStr(&'a str): {
+            r##"r#*""## => {
+                let hashes = text.len() - 2;
+                loop {
+                    cursor.eat_while(|c| c != '"');
+                    if cursor.bump().is_none() {
+                        return Err(cursor.error(LexErrorKind::UnexpectedEof { expected: &[] }).into());
+                    }
+                    let mut n = 0;
+                    while n < hashes && cursor.peek() == Some('#') {
+                        cursor.bump();
+                        n += 1;
+                    }
+                    if n == hashes {
+                        break RawStr(text);
+                    }
+                }
+            }
+        }
+        Comment(&'a str): {
+            r"/\*" => {
+                let mut depth = 1;
+                while depth > 0 {
+                    if cursor.starts_with("*/") {
+                        depth -= 1;
+                    } else if cursor.peek() == Some('/') && cursor.peek_n(1) == Some('*') {
+                        depth += 1;
+                    } else {
+                        cursor.bump().ok_or_else(|| cursor.error(LexErrorKind::UnexpectedEof { expected: &[] }))?;
+                        continue;
+                    }
+                    cursor.bump();
+                    cursor.bump();
+                }
+                Comment(text)
+            }
+        }
+        Sized(&'a str): {
+            r"\d+:" => {
+                let len: usize = text[..text.len() - 1].parse()?;
+                let start = text.len();
+                for _ in 0..len {
+                    if cursor.bump().is_none() {
+                        return Err(cursor.error(LexErrorKind::UnexpectedEof { expected: &[] }).into());
+                    }
+                }
+                Sized(&text[start..])
+            }
+        }
+        Var(&'a str): {
+            r"\$\w+" => {
+                // a local `cursor` shadows the cursor of the lexer.
+                let mut cursor = text.chars();
+                cursor.next();
+                Var(cursor.as_str())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Lexer)]
+#[token_derive(Debug, Clone, PartialEq)]
+#[skip(r"#[^\n]*")]
+#[context(usize)]
+pub enum Derived<'a> {
+    #[token("let")]
+    Let,
+    #[token("+")]
+    #[token("=")]
+    #[token("-", |lex| *lex.ctx() += 1)]
+    Op,
+    /// an integer
+    #[regex(r"[0-9]+", |lex| lex.text().parse())]
+    Int(u64),
+    #[regex(r"\w+")]
+    Ident(&'a str),
+    #[token("\"", |lex| {
+        let s = lex.cursor().eat_while(|c| c != '"');
+        match lex.cursor().bump() {
+            Some(_) => Ok(s),
+            None => Err(lex.cursor().error(LexErrorKind::UnexpectedEof { expected: &[] })),
+        }
+    })]
+    Str(&'a str),
+    #[group("(", ")")]
+    Paren(enum_lexer::Group<derived::Token<'a>>),
+}
+
+// the `TokenInner` of a derive is as visible as the enum.
+mod restricted {
+    pub mod inner {
+        use enum_lexer::Lexer;
+
+        #[derive(Debug, PartialEq, Lexer)]
+        pub(super) enum Outer {
+            #[token("a")]
+            A,
+        }
+
+        #[derive(Debug, PartialEq, Lexer)]
+        pub(in crate::restricted) enum InPath {
+            #[token("b")]
+            B,
+        }
+
+        #[derive(Debug, PartialEq, Lexer)]
+        enum Private {
+            #[token("c")]
+            C,
+        }
+
+        pub fn private() -> bool {
+            private::parse_str("c").unwrap().map(|t| t.unwrap().inner).eq([Private::C])
+        }
+    }
+
+    /// whether each lexer reads its token.
+    pub fn lex() -> bool {
+        inner::outer::parse_str("a").unwrap().map(|t| t.unwrap().inner).eq([inner::Outer::A])
+            && inner::in_path::parse_str("b").unwrap().map(|t| t.unwrap().inner).eq([inner::InPath::B])
+            && inner::private()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -60,11 +405,510 @@ mod test {
             .collect();
         let vec = vec.unwrap();
         assert_eq!{
-            vec,
-            vec![Let, Ident("a".into()), Op('='), LitStr("\"asdf\"".into()), Op('+'),
-            Group(vec![
-                LitInt(1), Op('+'), LitInt(2)
-            ], '(')]
+            vec[..5],
+            [Let, Ident("a".into()), Op('='), LitStr("\"asdf\"".into()), Op('+')]
+        }
+        match &vec[5] {
+            Group(group) => assert_eq!(
+                group.tokens.iter().map(|t| &t.inner).collect::<Vec<_>>(),
+                [&LitInt(1), &Op('+'), &LitInt(2)]
+            ),
+            inner => panic!("unexpected {:?}", inner),
+        }
+    }
+
+    #[test]
+    fn unicode_test() {
+        let vec: lexer::Result<Vec<_>> = lexer::parse_str(
+            "let s = \"héllo, 世界\" /* ünïcödé */ + 1 // ✓\nlet 变量 = λx"
+            ).unwrap()
+            .collect();
+        let vec = vec.unwrap();
+        assert_eq!{
+            vec.iter().map(|t| &t.inner).collect::<Vec<_>>(),
+            vec![&Let, &Ident("s".into()), &Op('='), &LitStr("\"héllo, 世界\"".into()), &Op('+'), &LitInt(1),
+                &Let, &Ident("变量".into()), &Op('='), &Ident("λx".into())]
+        }
+        assert_eq!(vec[4].span.start().column, 43);
+    }
+
+    #[test]
+    fn backtrack_test() {
+        let vec: lexer::Result<Vec<_>> = lexer::parse_str("--x --> -->-").unwrap()
+            .map(|result| result.map(|t| t.inner))
+            .collect();
+        assert_eq!{
+            vec.unwrap(),
+            vec![Op('-'), Op('-'), Ident("x".into()), Arrow, Arrow, Op('-')]
+        }
+
+        // non-greedy rules still take the shortest match.
+        let vec: lexer::Result<Vec<_>> = lexer::parse_str(r#""a" "b""#).unwrap()
+            .map(|result| result.map(|t| t.inner))
+            .collect();
+        assert_eq!(vec.unwrap(), vec![LitStr("\"a\"".into()), LitStr("\"b\"".into())]);
+
+        let vec: Vec<_> = lexer::parse_str("a --").unwrap().collect();
+        assert_eq!(vec.len(), 3);
+        assert_eq!(vec[2].as_ref().unwrap().span.start().column, 3);
+    }
+
+    #[test]
+    fn priority_test() {
+        let vec: lexer::Result<Vec<_>> = lexer::parse_str("def let define letter").unwrap()
+            .map(|result| result.map(|t| t.inner))
+            .collect();
+        assert_eq!{
+            vec.unwrap(),
+            vec![Def, Let, Ident("define".into()), Ident("letter".into())]
+        }
+    }
+
+    #[test]
+    fn mode_test() {
+        use interp::TokenInner::*;
+        let vec: interp::Result<Vec<_>> = interp::parse_str(r#"a "hi ${b "c${d}"} !" e"#).unwrap()
+            .map(|result| result.map(|t| t.inner))
+            .collect();
+        assert_eq!{
+            vec.unwrap(),
+            vec![Ident("a"), Quote, Text("hi "), Interp, Ident("b"),
+                Quote, Text("c"), Interp, Ident("d"), RBrace, Quote,
+                RBrace, Text(" !"), Quote, Ident("e")]
+        }
+
+        let mut iter = interp::parse_str("\"").unwrap();
+        assert_eq!(iter.mode(), interp::Mode::Initial);
+        iter.next();
+        assert_eq!(iter.mode(), interp::Mode::Str);
+        assert_eq!(iter.pop_mode(), Some(interp::Mode::Str));
+        assert_eq!(iter.pop_mode(), None);
+        iter.set_mode(interp::Mode::Str);
+        assert_eq!(iter.mode(), interp::Mode::Str);
+    }
+
+    #[test]
+    fn whitespace_test() {
+        use lines::TokenInner::*;
+        let vec: lines::Result<Vec<_>> = lines::parse_str("a b\t\n\nc ").unwrap()
+            .map(|result| result.map(|t| t.inner))
+            .collect();
+        assert_eq!(vec.unwrap(), vec![Word("a"), Word("b"), Newline, Newline, Word("c")]);
+
+        // form feed is not in the skip rule of `lines`, but is whitespace by default.
+        assert!(lines::parse_str("a\x0Cb").unwrap().nth(1).unwrap().is_err());
+        let vec: lexer::Result<Vec<_>> = lexer::parse_str("let\x0B\x0Ca\u{3000}=\u{2028}1").unwrap()
+            .map(|result| result.map(|t| t.inner))
+            .collect();
+        assert_eq!(vec.unwrap(), vec![Let, Ident("a".into()), Op('='), LitInt(1)]);
+    }
+
+    fn rebuild(src: &str, tokens: &[trivia::Token], out: &mut String) {
+        for token in tokens {
+            for t in &token.leading {
+                out.push_str(&src[t.span.byte_range()]);
+            }
+            if let trivia::TokenInner::Group(group) = &token.inner {
+                out.push_str(&src[group.open.byte_range()]);
+                rebuild(src, &group.tokens, out);
+                out.push_str(&src[group.close.byte_range()]);
+            } else {
+                out.push_str(&src[token.span.byte_range()]);
+            }
+            for t in &token.trailing {
+                out.push_str(&src[t.span.byte_range()]);
+            }
+        }
+    }
+
+    #[test]
+    fn lossless_test() {
+        use enum_lexer::TriviaKind::*;
+        let src = "  a  b // c\n\t(x // y\n z ) \n\x0C// end\n\n";
+        let tokens: trivia::Result<Vec<_>> = trivia::parse_str(src).unwrap().collect();
+        let tokens = tokens.unwrap();
+        assert_eq!(tokens.len(), 3);
+
+        let mut out = String::new();
+        rebuild(src, &tokens, &mut out);
+        assert_eq!(out, src);
+
+        let kinds = |trivia: &[enum_lexer::Trivia]| trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(&tokens[0].leading), vec![Whitespace]);
+        assert_eq!(kinds(&tokens[0].trailing), vec![Whitespace]);
+        assert_eq!(kinds(&tokens[1].trailing), vec![Whitespace, Comment, Whitespace]);
+        assert_eq!(kinds(&tokens[2].leading), vec![Whitespace]);
+        // the closing bracket is a part of the group, not trivia.
+        assert_eq!(kinds(&tokens[2].trailing), vec![Whitespace, Whitespace, Comment, Whitespace, Whitespace]);
+
+        // an input without tokens keeps its trivia on the iterator.
+        let src = " // x\n";
+        let mut iter = trivia::parse_str(src).unwrap();
+        assert!(iter.next().is_none());
+        let text: String = iter.trivia().iter().map(|t| &src[t.span.byte_range()]).collect();
+        assert_eq!(text, src);
+    }
+
+    #[test]
+    fn borrow_test() {
+        let src = String::from("hello world");
+        let words: Vec<&str> = lines::parse_str(&src).unwrap()
+            .map(|t| match t.unwrap().inner {
+                lines::TokenInner::Word(word) => word,
+                _ => panic!("expected a word"),
+            })
+            .collect();
+        assert_eq!(words, vec!["hello", "world"]);
+        assert!(std::ptr::eq(words[1].as_ptr(), src[6..].as_ptr()));
+    }
+
+    #[test]
+    fn context_test() {
+        use symbols::TokenInner::*;
+        let mut iter = symbols::parse_str_with_context("a b (a (c b)) d", Symbols::default()).unwrap();
+        let vec: symbols::Result<Vec<_>> = (&mut iter).map(|result| result.map(|t| t.inner)).collect();
+        assert_eq!(iter.into_ctx().names, vec!["a", "b", "c", "d"]);
+
+        let vec = vec.unwrap();
+        assert_eq!(vec.len(), 4);
+        assert_eq!((&vec[0], &vec[1], &vec[3]), (&Ident(0), &Ident(1), &Ident(3)));
+        match &vec[2] {
+            Group(group) => {
+                assert_eq!(group.tokens[0].inner, Ident(0));
+                assert!(matches!(&group.tokens[1].inner, Group(g) if g.tokens.len() == 2 && g.tokens[0].inner == Ident(2)));
+            }
+            _ => panic!("expected a group"),
+        }
+
+        let mut iter = symbols::parse_str_with_context("#abc a", Symbols::default()).unwrap();
+        assert_eq!(iter.next().unwrap().unwrap().inner, Hash(3));
+        assert_eq!(iter.next().unwrap().unwrap().inner, Ident(0));
+        assert_eq!(iter.into_ctx().names, vec!["a"]);
+    }
+
+    #[test]
+    fn read_group_test() {
+        use enum_lexer::{LexErrorKind, SpanError};
+        use nested::TokenInner::*;
+        let mut iter = nested::parse_str_with_context("a b (a (c b)) d", Symbols::default()).unwrap();
+        let vec: nested::Result<Vec<_>> = (&mut iter).map(|result| result.map(|t| t.inner)).collect();
+        assert_eq!(iter.ctx().depth, 0);
+        let ctx = iter.into_ctx();
+        assert_eq!(ctx.names, vec!["a", "b", "c", "d"]);
+        assert_eq!(ctx.max_depth, 2);
+
+        let vec = vec.unwrap();
+        assert_eq!(vec.len(), 4);
+        match &vec[2] {
+            Group(inner) => {
+                assert_eq!(inner[0].inner, Ident(0));
+                assert!(matches!(&inner[1].inner, Group(g) if g.len() == 2 && g[0].inner == Ident(2)));
+            }
+            _ => panic!("expected a group"),
+        }
+
+        let e = nested::parse_str_with_context("a (b c", Symbols::default()).unwrap().nth(1).unwrap().unwrap_err();
+        match &e.downcast_ref::<SpanError>().unwrap().kind {
+            LexErrorKind::UnclosedGroup { open } => assert_eq!(open.start().column, 2),
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn cursor_test() {
+        use scan::TokenInner::*;
+        let src = r###"a r#"b "c" d"# /* e /* f */ g */ 3:h i r"j""###;
+        let tokens: scan::Result<Vec<_>> = scan::parse_str(src).unwrap().collect();
+        let tokens = tokens.unwrap();
+        assert_eq!{
+            tokens.iter().map(|t| t.inner.clone()).collect::<Vec<_>>(),
+            vec![Ident("a"), RawStr(r##"r#"b "c" d"#"##), Comment("/* e /* f */ g */"),
+                Sized("h i"), RawStr(r#"r"j""#)]
+        }
+        assert_eq!(&src[tokens[2].span.byte_range()], "/* e /* f */ g */");
+        assert_eq!(tokens[3].span.end().column, src.len() - 5);
+
+        assert_eq!(scan::parse_str("$ab").unwrap().next().unwrap().unwrap().inner, Var("ab"));
+        assert_eq!(scan::parse_str("2:éa").unwrap().next().unwrap().unwrap().inner, Sized("éa"));
+        assert!(scan::parse_str("5:ab").unwrap().next().unwrap().is_err());
+
+        let tokens: Vec<_> = scan::parse_str("~~~ a").unwrap().map(|t| t.unwrap()).collect();
+        assert_eq!((&tokens[0].inner, tokens[0].span.end().column), (&Tildes(3), 3));
+
+        let mut cursor = enum_lexer::Cursor::new_file("<peek>", "éa");
+        assert_eq!(cursor.peek(), Some('é'));
+        assert_eq!(cursor.next(), Some('é'));
+        assert_eq!(cursor.peek(), Some('a'));
+        assert!(scan::parse_str("/* a /* b */").unwrap().next().unwrap().is_err());
+        assert!(scan::parse_str(r##"r#"a""##).unwrap().next().unwrap().is_err());
+    }
+
+    #[test]
+    fn recover_test() {
+        use recovering::TokenInner::*;
+        let src = "a ?? b+\"c \u{20ac}d\n\"e\"";
+        let tokens: recovering::Result<Vec<_>> = recovering::parse_str(src).unwrap().collect();
+        let tokens = tokens.unwrap();
+        assert_eq!{
+            tokens.iter().map(|t| &t.inner).collect::<Vec<_>>(),
+            vec![&Ident("a"), &Error("??"), &Ident("b"), &Plus, &Error("\""), &Ident("c"),
+                &Error("\u{20ac}"), &Ident("d"), &LitStr("\"e\"")]
+        }
+        assert_eq!(&src[tokens[1].span.byte_range()], "??");
+        assert_eq!(tokens[8].span.start().line, 2);
+
+        use unknown::lexer::TokenInner::{Unknown, Word};
+        let tokens: Vec<_> = unknown::lexer::parse_str("a ! b").unwrap().map(|t| t.unwrap().inner).collect();
+        assert_eq!(tokens, vec![Word("a"), Unknown("!"), Word("b")]);
+        assert_eq!(unknown::lexer::TokenKind::Unknown.name(), "Unknown");
+        assert_eq!(unknown::plain::parse_str("a !").unwrap().nth(1).unwrap().unwrap().kind(), unknown::plain::TokenKind::Error);
+        assert!(unknown::errors("a ! b").is_empty());
+        // a handler makes the renamed token by its bare name.
+        let tokens: Vec<_> = unknown::lexer::parse_str("'ab' 'abcdefghij'").unwrap().map(|t| t.unwrap().inner).collect();
+        assert_eq!(tokens, vec![unknown::lexer::TokenInner::Quoted("'ab'"), Unknown("'abcdefghij'")]);
+
+        // the error tokens keep the input whole.
+        let src = "a ?(b ?\n) c??";
+        let tokens: trivia::Result<Vec<_>> = trivia::parse_str(src).unwrap().collect();
+        let tokens = tokens.unwrap();
+        let mut out = String::new();
+        rebuild(src, &tokens, &mut out);
+        assert_eq!(out, src);
+        assert_eq!(tokens[1].inner, trivia::TokenInner::Error("?".into()));
+        assert_eq!(tokens[4].inner, trivia::TokenInner::Error("??".into()));
+    }
+
+    #[test]
+    fn error_test() {
+        use enum_lexer::{LexErrorKind, SpanError};
+        let span_error = |e: &lexer::LexError| -> String {
+            let e = e.downcast_ref::<SpanError>().unwrap();
+            format!("{} {:?}", e.text, e.kind)
+        };
+
+        let vec: Vec<_> = lexer::parse_str("let # a").unwrap().collect();
+        assert_eq!(vec.len(), 3);
+        let e = vec[1].as_ref().unwrap_err().downcast_ref::<SpanError>().unwrap();
+        assert!(matches!(&e.kind, LexErrorKind::UnexpectedChar { ch: '#', expected } if expected.iter().any(|r| r.contains(&'5'))));
+        assert_eq!(e.span.start().column, 4);
+        assert!(e.to_string().starts_with("<string>:1:5: unexpected char '#', expected '!'..='\"', '('..=')'"), "{}", e);
+        // lexing goes on after the error.
+        assert_eq!(vec[2].as_ref().unwrap().inner, Ident("a".into()));
+        assert!(enum_lexer::diagnostic::Diagnostic::from(e).render("let # a").to_string().starts_with("\
+error: unexpected char '#'
+ --> <string>:1:5
+  |
+1 | let # a
+  |     ^
+  = note: expected '!'..='\"', '('..=')', '+', '-', '/'..='9', '=', 'A'..='Z', '_' or "));
+
+        // both headers give the column in bytes, the marks are under the char.
+        let src = "λé # a";
+        let e = lexer::parse_str(src).unwrap().nth(1).unwrap().unwrap_err();
+        let e = e.downcast_ref::<SpanError>().unwrap();
+        assert_eq!(e.span.start().column, 5);
+        assert!(e.to_string().starts_with("<string>:1:6: "), "{}", e);
+        assert!(enum_lexer::diagnostic::Diagnostic::from(e).render(src).to_string().starts_with("\
+error: unexpected char '#'
+ --> <string>:1:6
+  |
+1 | λé # a
+  |    ^
+"));
+
+        // a `?` in a handler gives the error with the span of the token.
+        let vec: Vec<_> = lexer::parse_str("a 99999999999999999999").unwrap().collect();
+        assert_eq!(span_error(vec[1].as_ref().unwrap_err()), "99999999999999999999 Handler(ParseIntError { kind: PosOverflow })");
+        assert_eq!(vec[1].as_ref().unwrap_err().to_string(), "<string>:1:3: number too large to fit in target type");
+
+        let vec: Vec<_> = lexer::parse_str("a \"bc").unwrap().collect();
+        assert!(span_error(vec[1].as_ref().unwrap_err()).starts_with("\"bc UnexpectedEof"));
+
+        let e = lexer::parse_str("1 (2 3").unwrap().nth(1).unwrap().unwrap_err();
+        match &e.downcast_ref::<SpanError>().unwrap().kind {
+            LexErrorKind::UnclosedGroup { open } => assert_eq!(open.start().column, 2),
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn rename_test() {
+        use words::lexer::{Words, WordKind};
+        let iter: Words = words::lexer::lex("a (b c)").unwrap();
+        let vec: words::lexer::Result<Vec<_>> = iter.map(|result| result.map(|t| t.inner)).collect();
+        let vec = vec.unwrap();
+        assert_eq!(vec[0], WordKind::Word("a"));
+        assert!(matches!(&vec[1], WordKind::Pair(p) if p.tokens.len() == 2 && p.tokens[1].inner == WordKind::Word("c")));
+        assert!(words::lexer::lex_with_name("words.txt", "a").unwrap().next().is_some());
+
+        let vec: Vec<_> = items::parse_str("mode lexer").unwrap().map(|t| t.unwrap().inner).collect();
+        assert_eq!(vec, vec![items::TokenInner::Mode, items::TokenInner::Lexer]);
+    }
+
+    #[test]
+    fn derive_test() {
+        use super::Derived::*;
+        let mut iter = derived::parse_str_with_context("let a = 1 - \"b c\" # d\n+ letter", 0).unwrap();
+        let vec: Vec<_> = iter.by_ref().map(|t| t.unwrap().inner).collect();
+        assert_eq!(vec, vec![Let, Ident("a"), Op, Int(1), Op, Str("b c"), Op, Ident("letter")]);
+        assert_eq!(*iter.ctx(), 1);
+
+        let vec: Vec<_> = derived::parse_str_with_context("99999999999999999999 \"a", 0).unwrap().collect();
+        // the error of a callback gets the span and the text of its token.
+        let e = vec[0].as_ref().unwrap_err().downcast_ref::<enum_lexer::SpanError>().unwrap();
+        assert!(matches!(&e.kind, enum_lexer::LexErrorKind::Handler(e) if e.is::<std::num::ParseIntError>()));
+        assert_eq!((e.span.start().column, e.text.as_str()), (0, "99999999999999999999"));
+        // an error that is already a `SpanError` is kept.
+        let e = vec[1].as_ref().unwrap_err().downcast_ref::<enum_lexer::SpanError>().unwrap();
+        assert!(matches!(e.kind, enum_lexer::LexErrorKind::UnexpectedEof { .. }));
+        assert!(super::restricted::lex());
+    }
+
+    /// the number of tokens in `src`, or the first error.
+    fn count<L: enum_lexer::Lexer>(src: &str) -> Result<usize, L::Error>
+    where
+        L::Context: Default,
+    {
+        let mut n = 0;
+        for token in L::lex(src)? {
+            token?;
+            n += 1;
         }
+        Ok(n)
+    }
+
+    #[test]
+    fn trait_test() {
+        assert_eq!(count::<lexer::Lexer>("let a = 1 + b").ok(), Some(6));
+        assert_eq!(count::<derived::Lexer>("let a = 1").ok(), Some(4));
+        assert_eq!(count::<words::lexer::Lexer>("a (b c) d").ok(), Some(3));
+        assert!(count::<lexer::Lexer>("let # a").is_err());
+
+        use enum_lexer::Lexer;
+        let iter = symbols::Lexer::lex_with_context("syms.txt", "a (b a)", Symbols::default()).unwrap();
+        let tokens: Vec<_> = iter.collect::<Result<_, _>>().unwrap();
+        assert_eq!(tokens[0].span.source_name(), "syms.txt");
+    }
+
+    #[test]
+    fn kind_test() {
+        use lexer::TokenKind;
+        use std::collections::HashSet;
+        let kinds: Vec<_> = lexer::parse_str("let a = (1)").unwrap().map(|t| t.unwrap().kind()).collect();
+        assert_eq!(kinds, [TokenKind::Let, TokenKind::Ident, TokenKind::Op, TokenKind::Group]);
+        let expected: HashSet<_> = [TokenKind::Ident, TokenKind::Let].iter().copied().collect();
+        assert!(expected.contains(&kinds[1]));
+        assert_eq!(TokenKind::ALL.len(), 8);
+        assert_eq!(TokenKind::ALL.iter().map(|k| k.name()).collect::<Vec<_>>(),
+            ["Def", "Ident", "LitStr", "LitInt", "Arrow", "Let", "Op", "Group"]);
+
+        // the kind of `#[recover]` errors.
+        assert_eq!(trivia::TokenInner::Error("?".into()).kind(), trivia::TokenKind::Error);
+        assert_eq!(derived::TokenKind::ALL.last().unwrap().name(), "Paren");
+        assert_eq!(Derived::Int(1).kind(), derived::TokenKind::Int);
+    }
+
+    #[test]
+    fn describe_test() {
+        use lexer::TokenKind;
+        assert_eq!(TokenKind::Def.describe(), "`def`");
+        assert_eq!(TokenKind::Arrow.describe(), "`-->`");
+        assert_eq!(TokenKind::LitInt.describe(), "integer literal");
+        // several rules and no description.
+        assert_eq!(TokenKind::Op.describe(), "Op");
+        let tokens: Vec<_> = lexer::parse_str("let 1").unwrap().map(|t| t.unwrap().to_string()).collect();
+        assert_eq!(tokens, ["`let`", "integer literal"]);
+        assert_eq!(format!("expected {}", TokenKind::Let), "expected `let`");
+
+        assert_eq!(derived::TokenKind::Int.describe(), "an integer");
+        assert_eq!(derived::TokenKind::Let.describe(), "`let`");
+        assert_eq!(trivia::TokenKind::Error.describe(), "unknown text");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn group_test() {
+        use brackets::TokenInner::{Block, Group, Word};
+        let src = "a (b [c] # )\n{d}) begin e end";
+        let tokens: Vec<_> = brackets::parse_str(src).unwrap()
+            .collect::<brackets::Result<_>>().unwrap();
+        assert_eq!(tokens.len(), 3);
+        let group = match &tokens[1].inner {
+            Group(group) => group,
+            inner => panic!("unexpected {:?}", inner),
+        };
+        assert_eq!(group.delimiters, ("(", ")"));
+        assert_eq!((group.open.start().column, group.close.start().column), (2, 3));
+        assert_eq!(&src[tokens[1].span.byte_range()], "(b [c] # )\n{d})");
+        // the comment in the group is kept by the token before it.
+        assert_eq!(group.tokens[1].trailing.len(), 3);
+        assert_eq!(tokens[1].leading.len(), 0);
+        assert_eq!(tokens[1].trailing.len(), 1);
+        let inner: Vec<_> = group.tokens.iter().map(|t| t.inner.clone()).collect();
+        assert!(matches!(&inner[..], [Word("b"), Group(g1), Group(g2)]
+            if g1.delimiters.0 == "[" && g1.tokens[0].inner == Word("c") && g2.delimiters.0 == "{"));
+        assert!(matches!(&tokens[2].inner, Block(g) if g.tokens.len() == 1));
+        assert_eq!(tokens[2].kind(), brackets::TokenKind::Block);
+
+        // groups are read in a loop, not by recursion.
+        let src = format!("{}{}", "(".repeat(1000), ")".repeat(1000));
+        assert_eq!(brackets::parse_str(&src).unwrap().count(), 1);
+
+        let d = Derived::Paren(enum_lexer::Group { delimiters: ("(", ")"), open: tokens[0].span, close: tokens[0].span, tokens: vec![] });
+        assert_eq!(d.kind(), derived::TokenKind::Paren);
+        let tokens: Vec<_> = derived::parse_str_with_context("(1 (a))", 0).unwrap().collect::<Result<_, _>>().unwrap();
+        assert!(matches!(&tokens[0].inner, Derived::Paren(g) if g.tokens.len() == 2));
+    }
+
+    #[test]
+    fn group_error_test() {
+        use enum_lexer::{diagnostic::Diagnostic, LexErrorKind, SpanError};
+        let error = |e: &brackets::LexError| e.downcast_ref::<SpanError>().unwrap().kind.to_string();
+
+        let src = "(a\n  b]";
+        let vec: Vec<_> = brackets::parse_str(src).unwrap().collect();
+        let e = vec[0].as_ref().unwrap_err().downcast_ref::<SpanError>().unwrap();
+        match &e.kind {
+            LexErrorKind::MismatchedGroup { open, expected } => {
+                assert_eq!((open.start().line, open.start().column, *expected), (1, 0, ")"));
+            }
+            kind => panic!("unexpected {:?}", kind),
+        }
+        assert_eq!(e.text, "]");
+        assert_eq!(Diagnostic::from(e).render(src).to_string(), "\
+error: mismatched closing bracket `]`
+ --> <string>:2:4
+  |
+2 |   b]
+  |    ^ expected `)`
+ ::: <string>:1:1
+  |
+1 | (a
+  | - the group opened here
+");
+
+        // lexing goes on after a stray closing bracket.
+        let vec: Vec<_> = brackets::parse_str("a ) b end").unwrap().collect();
+        assert_eq!(vec.len(), 4);
+        assert_eq!(error(vec[1].as_ref().unwrap_err()), "unexpected closing bracket");
+        assert_eq!(vec[2].as_ref().unwrap().inner, brackets::TokenInner::Word("b"));
+        assert_eq!(error(vec[3].as_ref().unwrap_err()), "unexpected closing bracket");
+
+        // the group stays open after a mismatched bracket, and is closed later.
+        let vec: Vec<_> = brackets::parse_str("(a ] )").unwrap().collect();
+        assert_eq!(vec.len(), 2);
+        assert_eq!(error(vec[0].as_ref().unwrap_err()), "mismatched closing bracket, expected `)`");
+        assert!(matches!(&vec[1].as_ref().unwrap().inner, brackets::TokenInner::Group(g) if g.tokens.len() == 1));
+        let vec: Vec<_> = brackets::parse_str("(a ]").unwrap().collect();
+        assert_eq!(vec.len(), 2);
+        assert!(matches!(
+            &vec[1].as_ref().unwrap_err().downcast_ref::<SpanError>().unwrap().kind,
+            LexErrorKind::UnclosedGroup { open } if open.start().column == 0
+        ));
+
+        let e = brackets::parse_str("(a [b").unwrap().next().unwrap().unwrap_err();
+        match &e.downcast_ref::<SpanError>().unwrap().kind {
+            LexErrorKind::UnclosedGroup { open } => assert_eq!(open.start().column, 3),
+            kind => panic!("unexpected {:?}", kind),
+        }
+    }
+}
@@ -31,7 +31,7 @@ mod lexer {
     impl<'a> TokenIterator<'a> {
         #[inline(always)]
         fn dfa_state_0(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Next(1usize))
@@ -60,7 +60,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_1(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(0usize))
@@ -82,7 +82,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_2(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(0usize))
@@ -104,7 +104,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_3(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(0usize))
@@ -126,7 +126,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_4(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(0usize))
@@ -148,7 +148,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_5(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(0usize))
@@ -170,7 +170,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_6(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(0usize))
@@ -192,7 +192,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_7(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{41}'..'\u{5b}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(0usize))
@@ -214,7 +214,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_8(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{0}'..'\u{22}') => {
                     self.cursor.next();
                     Ok(StateNext::Next(9usize))
@@ -235,7 +235,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_9(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{0}'..'\u{22}') => {
                     self.cursor.next();
                     Ok(StateNext::Next(9usize))
@@ -256,7 +256,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_10(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{0}'..'\u{22}') => {
                     self.cursor.next();
                     Ok(StateNext::Final(1usize))
@@ -274,7 +274,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_11(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{30}'..'\u{3a}') => {
                     self.cursor.next();
                     Ok(StateNext::Next(12usize))
@@ -284,7 +284,7 @@ mod lexer {
         }
         #[inline(always)]
         fn dfa_state_12(&mut self) -> Result<StateNext> {
-            match self.cursor.peek().copied() {
+            match self.cursor.peek() {
                 Some(ch @ '\u{30}'..'\u{3a}') => {
                     self.cursor.next();
                     Ok(StateNext::Next(12usize))
@@ -9,7 +9,9 @@
 //! 
 
 use std::str::FromStr;
+use std::iter::Peekable;
 use crate::set::*;
+use crate::unicode::property_ranges;
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum AstNode {
     Char(CharRange),
@@ -20,6 +22,40 @@ pub enum AstNode {
     Concat(Vec<AstNode>),
     // NamedConcat(Vec<AstNode>, String),
 }
+
+impl AstNode {
+    /// whether the regex matches the empty string.
+    pub fn is_nullable(&self) -> bool {
+        use AstNode::*;
+        match self {
+            Char(_) => false,
+            Options(vec) => vec.iter().any(|n| n.is_nullable()),
+            Multiple(_) | EmptyOr(_) | MultipleNonGreedy(_) => true,
+            Concat(vec) => vec.iter().all(|n| n.is_nullable()),
+        }
+    }
+
+    /// the only text the regex matches, when it is a plain text like `def`.
+    pub fn literal(&self) -> Option<String> {
+        use AstNode::*;
+        match self {
+            Char(range) if range.end == range.start + 1 => char::from_u32(range.start).map(String::from),
+            Concat(vec) => vec.iter().map(|n| n.literal()).collect(),
+            _ => None,
+        }
+    }
+
+    /// the number of nodes in the tree.
+    fn size(&self) -> usize {
+        use AstNode::*;
+        match self {
+            Char(_) => 1,
+            Options(vec) | Concat(vec) => 1 + vec.iter().map(|n| n.size()).sum::<usize>(),
+            Multiple(n) | EmptyOr(n) | MultipleNonGreedy(n) => 1 + n.size(),
+        }
+    }
+}
+
 use thiserror::Error as ThisError;
 
 #[derive(Debug, ThisError)]
@@ -38,6 +74,43 @@ pub enum Error {
     UnexpectedChar(usize, char),
     #[error("found an empty string")]
     EmptyString,
+    #[error("unknown unicode property '{1}' at {0}")]
+    UnknownProperty(usize, String),
+    #[error("repetition at {0} is larger than {} or expands to more than {} nodes", REPEAT_LIMIT, SIZE_LIMIT)]
+    RepeatTooLarge(usize),
+    #[error("invalid repetition range at {0}")]
+    InvalidRepeat(usize),
+    #[error("repetition at {0} repeats nothing, it has a count of 0")]
+    EmptyRepeat(usize),
+    #[error("invalid char range at {0}")]
+    InvalidRange(usize),
+    #[error("invalid code point at {0}")]
+    InvalidCodePoint(usize),
+    #[error("char class at {0} matches nothing")]
+    EmptyClass(usize),
+}
+
+impl Error {
+    /// the char offset in the regex where the error is found.
+    pub fn pos(&self) -> Option<usize> {
+        use Error::*;
+        match self {
+            MissingExpresion(pos)
+            | MissingFirstExpr(pos)
+            | ExceptNotUsable(pos)
+            | UnmatchedChar(pos, _)
+            | UnexpectedEnd(pos)
+            | UnexpectedChar(pos, _)
+            | UnknownProperty(pos, _)
+            | RepeatTooLarge(pos)
+            | InvalidRepeat(pos)
+            | EmptyRepeat(pos)
+            | InvalidRange(pos)
+            | InvalidCodePoint(pos)
+            | EmptyClass(pos) => Some(*pos),
+            EmptyString => None,
+        }
+    }
 }
 
 // impl std::error::Error for Error {}
@@ -57,6 +130,11 @@ pub enum Error {
 // }
 
 pub type Result<T> = std::result::Result<T,Error>;
+
+/// the largest count allowed in `{n,m}`, each repetition copies the element into the automata.
+pub const REPEAT_LIMIT: usize = 1000;
+/// the largest tree a repetition may expand to, so that nested ones like `(a{1000}){1000}` are bounded too.
+pub const SIZE_LIMIT: usize = 100_000;
 pub trait CharStream: Iterator<Item=char> {}
 
 
@@ -65,11 +143,27 @@ pub trait CharStream: Iterator<Item=char> {}
 /// ```c
 /// Tree -> Option '|' ... '|' Option
 /// Option -> Element ... Element
-/// Element -> '(' Tree ')' | char | [char*] | '^'Element | Element'*'
+/// Element -> '(' Tree ')' | char | [char*] | [^char*] | '^'Element | Element'*'
+///          | Element'{n}' | Element'{n,}' | Element'{n,m}'
 /// ```
+///
+/// a `{` not followed by a digit is a plain char. a count of 0, as in `a{0}`
+/// or `a{0,0}`, is an error, and so is a `{n}` after another repetition.
+///
+/// escapes are `\n`, `\t`, `\r`, code points `\x7F` / `\u{1F600}`, the ascii
+/// classes `\d`, `\w`, `\s`, unicode properties `\p{Name}` / `\pL` and the
+/// negations `\D`, `\W`, `\S`, `\P{Name}`. any other escaped char is taken
+/// literally, also inside `[...]`.
+///
+/// though the regex reads any unicode char, `\d`, `\w` and `\s` only match
+/// ascii chars, e.g. `\d` is `[0-9]`. `\p{Nd}`, `\p{XID_Continue}` or
+/// `\p{White_Space}` match their unicode counterparts.
+///
+/// in `[...]`, a `-` with no char before it or right before the `]` is a
+/// plain char, as in `[-+]`, `[\d-]` or `[a-z-]`.
 struct Parser<Iter : CharStream> {
     first : char,
-    iter : Iter,
+    iter : Peekable<Iter>,
     pos: usize,
 }
 
@@ -79,7 +173,7 @@ impl<Iter : CharStream> Parser<Iter> {
     pub fn new(mut iter: Iter) -> Result<Self> {
         Ok(Self {
             first: iter.next().ok_or(Error::EmptyString)?,
-            iter,
+            iter: iter.peekable(),
             pos: 0,
         })
     }
@@ -114,7 +208,7 @@ impl<Iter : CharStream> Parser<Iter> {
             self.next_matches('|');
         }
 
-        if ret.len() == 0 {
+        if ret.is_empty() {
             return Err(Error::MissingExpresion(self.pos));
         }
 
@@ -140,7 +234,7 @@ impl<Iter : CharStream> Parser<Iter> {
             // no self.next()
         }
 
-        if ret.len() == 0 {
+        if ret.is_empty() {
             return Err(Error::MissingExpresion(self.pos));
         }
 
@@ -152,8 +246,10 @@ impl<Iter : CharStream> Parser<Iter> {
     }
 
     /// read an elemnt in parser.(`Element -> '(' Tree ')' | char | [char*] | '^'Element | Element'*'`)
+    ///
+    /// `'^'` negates a char, an escape or a char set, like `[^...]` does.
     pub fn parse_element(&mut self) -> Result<AstNode> {
-
+        let pos = self.pos;
         let mut is_except = false;
         if self.first == '^'{
             is_except = true;
@@ -167,13 +263,16 @@ impl<Iter : CharStream> Parser<Iter> {
                 }
                 self.next_matches('('); // parse_tree known nothings about this '(' ')'
                 let ret = self.parse_tree(true)?;
+                if self.first != ')' {
+                    return Err(Error::UnexpectedEnd(self.pos));
+                }
                 self.next_matches(')');
                 ret
             },
             '[' => {
                 // parse_charset know about these '[' ']'
-                let ret = self.parse_charset(is_except)?;
-                ret
+                let (negated, ranges) = self.parse_charset()?;
+                class_node(pos, ranges, is_except != negated)?
             },
             '.' => {
                 if is_except {
@@ -187,22 +286,18 @@ impl<Iter : CharStream> Parser<Iter> {
                 return Err(Error::UnexpectedChar(self.pos, self.first));
             }
             '\\' => {
-                let c = self.next();
-                self.next();
-                let c = match c {
-                    'n' => '\n',
-                    't' => '\t',
-                    'r' => '\r',
-                    _ => c
-                };
-                AstNode::Char(c..add1(c))
+                let ranges = self.parse_escape()?;
+                class_node(pos, ranges, is_except)?
             }
             c => {
                 self.next();
-                AstNode::Char(c..add1(c))
+                class_node(pos, vec![char_range(c)], is_except)?
             }
         };
 
+        if self.first == '{' && self.iter.peek().is_some_and(char::is_ascii_digit) {
+            ret = self.parse_repeat(ret)?;
+        }
         if self.first == '*' {
             self.next_matches('*');
             if self.first == '?' {
@@ -223,66 +318,269 @@ impl<Iter : CharStream> Parser<Iter> {
             self.next_matches('?');
             ret = AstNode::EmptyOr(Box::new(ret));
         }
+        // like `a**`, a repetition of a repetition is not read as text.
+        if self.first == '{' && self.iter.peek().is_some_and(char::is_ascii_digit) {
+            return Err(Error::UnexpectedChar(self.pos, '{'));
+        }
         Ok(ret)
     }
 
-    fn parse_charset(&mut self, is_except: bool) -> Result<AstNode> {
+    /// read a counted repetition `{n}`, `{n,}` or `{n,m}` of `node`.
+    fn parse_repeat(&mut self, node: AstNode) -> Result<AstNode> {
+        let pos = self.pos;
+        self.next_matches('{');
+        let min = self.parse_number()?;
+        let max = if self.first == ',' {
+            self.next_matches(',');
+            if self.first == '}' { None } else { Some(self.parse_number()?) }
+        } else {
+            Some(min)
+        };
+        match self.first {
+            '}' => { self.next_matches('}'); }
+            '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+            c => { return Err(Error::UnexpectedChar(self.pos, c)); }
+        }
+        if max.unwrap_or(min) > REPEAT_LIMIT {
+            return Err(Error::RepeatTooLarge(pos));
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err(Error::InvalidRepeat(pos));
+        }
+        if max == Some(0) {
+            return Err(Error::EmptyRepeat(pos));
+        }
+        let copies = max.unwrap_or(min + 1);
+        if copies.saturating_mul(node.size()) > SIZE_LIMIT {
+            return Err(Error::RepeatTooLarge(pos));
+        }
+
+        let mut ret = vec![node.clone(); min];
+        match max {
+            None => ret.push(AstNode::Multiple(Box::new(node))),
+            Some(max) => {
+                // `a{1,3}` is `a(a(a)?)?`, which keeps the nfa linear in `max`.
+                let mut optional = None;
+                for _ in min..max {
+                    optional = Some(AstNode::EmptyOr(Box::new(match optional {
+                        None => node.clone(),
+                        Some(rest) => AstNode::Concat(vec![node.clone(), rest]),
+                    })));
+                }
+                ret.extend(optional);
+            }
+        }
+        if ret.len() == 1 {
+            Ok(ret.pop().unwrap())
+        } else {
+            Ok(AstNode::Concat(ret))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<usize> {
+        let mut ret: Option<usize> = None;
+        while let Some(d) = self.first.to_digit(10) {
+            ret = Some(ret.unwrap_or(0).saturating_mul(10).saturating_add(d as usize));
+            self.next();
+        }
+        match (ret, self.first) {
+            (Some(n), _) => Ok(n),
+            (None, '\0') => Err(Error::UnexpectedEnd(self.pos)),
+            (None, c) => Err(Error::UnexpectedChar(self.pos, c)),
+        }
+    }
+
+    /// read an escape sequence starting at `\\`, as a set of ranges.
+    fn parse_escape(&mut self) -> Result<Vec<CharRange>> {
+        let pos = self.pos;
+        let c = self.next();
+        self.next();
+        let c = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'x' => self.parse_hex(pos, Some(2))?,
+            'u' => {
+                match self.first {
+                    '{' => { self.next_matches('{'); }
+                    '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+                    c => { return Err(Error::UnexpectedChar(self.pos, c)); }
+                }
+                let c = self.parse_hex(pos, None)?;
+                match self.first {
+                    '}' => { self.next_matches('}'); }
+                    '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+                    c => { return Err(Error::UnexpectedChar(self.pos, c)); }
+                }
+                c
+            }
+            'd' | 'w' | 's' => { return Ok(ascii_class(c)); }
+            'D' | 'W' | 'S' => { return Ok(complement(&ascii_class(c.to_ascii_lowercase()))); }
+            'p' => { return self.parse_property(pos); }
+            'P' => { return Ok(complement(&self.parse_property(pos)?)); }
+            '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+            _ => c
+        };
+        Ok(vec![char_range(c)])
+    }
+
+    /// read a hex code point, exactly `len` digits if given, otherwise 1 to 6.
+    fn parse_hex(&mut self, pos: usize, len: Option<usize>) -> Result<char> {
+        let mut value = 0;
+        let mut count = 0;
+        while count < len.unwrap_or(6) {
+            match self.first.to_digit(16) {
+                Some(d) => {
+                    value = value * 16 + d;
+                    count += 1;
+                    self.next();
+                }
+                None if len.is_none() && count > 0 => { break; }
+                None if self.first == '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+                None => { return Err(Error::UnexpectedChar(self.pos, self.first)); }
+            }
+        }
+        char::from_u32(value).ok_or(Error::InvalidCodePoint(pos))
+    }
+
+    /// read the name of `\p{Name}` or `\pL` after the `p`. `pos` is where the escape starts.
+    fn parse_property(&mut self, pos: usize) -> Result<Vec<CharRange>> {
+        let name = if self.first == '{' {
+            let mut name = String::new();
+            loop {
+                match self.next() {
+                    '}' => { break; }
+                    '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+                    c => { name.push(c); }
+                }
+            }
+            self.next_matches('}');
+            name
+        } else if self.first == '\0' {
+            return Err(Error::UnexpectedEnd(self.pos));
+        } else {
+            let name = self.first.to_string();
+            self.next();
+            name
+        };
+        property_ranges(&name).ok_or(Error::UnknownProperty(pos, name))
+    }
+
+    /// read `[...]` or `[^...]`, returns whether it is negated and its ranges.
+    fn parse_charset(&mut self) -> Result<(bool, Vec<CharRange>)> {
         self.next_matches('[');
+        let negated = self.first == '^';
+        if negated {
+            self.next_matches('^');
+        }
         let mut ret = Vec::<CharRange>::new();
+        // the char just read, which can start a range.
+        let mut last: Option<u32> = None;
         loop {
             match self.first {
                 ']' => { 
                     self.next_matches(']');
                     break;
                 },
+                // a `-` right before `]` or after no char is a plain char,
+                // e.g. `[+-]`, `[-+]` or `[\d-z]`.
+                '-' if self.iter.peek() == Some(&']') || last.is_none() => {
+                    ret.push(char_range('-'));
+                    last = Some('-' as u32);
+                },
                 '-' => {
-                    if let Some(range) = ret.pop() {
-                        if add1(range.start) == range.end {
+                    let pos = self.pos;
+                    let start = last.take().unwrap();
+                    ret.pop();
+                    // the end of a range can be escaped too, e.g. `[\x00-\x1F]`.
+                    let end = match self.next() {
+                        '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+                        '\\' => match self.parse_escape()?.as_slice() {
+                            [range] if range.start + 1 == range.end => range.start,
+                            _ => { return Err(Error::InvalidRange(pos)); }
+                        },
+                        c => {
                             self.next();
-                            ret.push(range.start..add1(self.first));
-                        } else {
-                            return Err(Error::MissingFirstExpr(self.pos));
+                            c as u32
                         }
-                    } else {
-                        return Err(Error::MissingFirstExpr(self.pos));
+                    };
+                    if end < start {
+                        return Err(Error::InvalidRange(pos));
                     }
+                    ret.push(start..end + 1);
+                    continue;
+                }
+                '\\' => {
+                    let ranges = self.parse_escape()?;
+                    last = match ranges.as_slice() {
+                        [range] if range.start + 1 == range.end => Some(range.start),
+                        _ => None,
+                    };
+                    ret.extend(ranges);
+                    continue;
                 }
-                c => { ret.push(c..add1(c)) },
+                '\0' => { return Err(Error::UnexpectedEnd(self.pos)); }
+                c => {
+                    ret.push(char_range(c));
+                    last = Some(c as u32);
+                },
             }
             self.next();
         }
         
-        if ret.len() == 0{
+        if ret.is_empty(){
             return Err(Error::MissingExpresion(self.pos));
         }
 
-        if is_except {
-            if ret.len() == 1 {
-                let s = ret.pop().unwrap();
-                return Ok(AstNode::Options(vec![
-                    AstNode::Char(
-                        CHAR_MIN..s.start
-                    ),
-                    AstNode::Char(
-                        s.end..CHAR_MAX
-                    ),
-                ]))
-            }
-            panic!("not implemented!");
-        }
+        Ok((negated, ret))
+    }
+}
 
-        if ret.len() == 1 {
-            Ok(AstNode::Char(ret.pop().unwrap()))
-        } else {
-            Ok(AstNode::Options(
-                ret.iter().map(|c| AstNode::Char(c.clone())).collect()
-            ))
+/// the ascii ranges of `\d`, `\w` and `\s`.
+fn ascii_class(c: char) -> Vec<CharRange> {
+    let ranges: &[(char, char)] = match c {
+        'd' => &[('0', '9')],
+        'w' => &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+        's' => &[('\t', '\r'), (' ', ' ')],
+        _ => unreachable!(),
+    };
+    ranges.iter().map(|&(lo, hi)| lo as u32..add1(hi)).collect()
+}
+
+/// a node matching any char in `ranges`, or any char not in `ranges` if `negated`.
+///
+/// `pos` is where the class starts.
+fn class_node(pos: usize, mut ranges: Vec<CharRange>, negated: bool) -> Result<AstNode> {
+    if negated {
+        ranges = complement(&ranges);
+        ranges.retain(|r| char_bounds(r).is_some());
+        if ranges.is_empty() {
+            return Err(Error::EmptyClass(pos));
         }
     }
+    if ranges.len() == 1 {
+        Ok(AstNode::Char(ranges.pop().unwrap()))
+    } else {
+        Ok(AstNode::Options(
+            ranges.into_iter().map(AstNode::Char).collect()
+        ))
+    }
 }
 
 impl CharStream for core::str::Chars<'_> {}
 
+/// the regex matching exactly `text`.
+pub fn escape(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_punctuation() {
+            ret.push('\\');
+        }
+        ret.push(c);
+    }
+    ret
+}
+
 impl FromStr for AstNode {
     type Err = Error;
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
@@ -298,9 +596,9 @@ mod test {
     use std::assert;
     use AstNode::*;
 
-    fn charnode(c : char) -> AstNode { Char(c..add1(c)) }
+    fn charnode(c : char) -> AstNode { Char(char_range(c)) }
     fn charrange(c : char, d : char) -> AstNode {
-        Char(c..add1(d))
+        Char(c as u32..add1(d))
     }
 
     fn multi(n : AstNode) -> AstNode { Multiple(Box::new(n)) }
@@ -308,6 +606,26 @@ mod test {
 
 
 
+    #[test]
+    fn escape_text() {
+        assert_eq!(escape("a+(b)"), r"a\+\(b\)");
+        let ast = escape(r"[.*?]\{1}^$").parse::<AstNode>().unwrap();
+        let chars: Vec<_> = r"[.*?]\{1}^$".chars().map(charnode).collect();
+        assert_eq!(ast, Concat(chars));
+    }
+
+    #[test]
+    fn literal() {
+        let literal = |regex: &str| regex.parse::<AstNode>().unwrap().literal();
+        assert_eq!(literal(r"def"), Some("def".to_string()));
+        assert_eq!(literal(r"\-\->"), Some("-->".to_string()));
+        assert_eq!(literal(&escape("a+(b)")), Some("a+(b)".to_string()));
+        assert_eq!(literal(r"[a]"), Some("a".to_string()));
+        assert_eq!(literal(r"de?f"), None);
+        assert_eq!(literal(r"a|b"), None);
+        assert_eq!(literal(r"[ab]"), None);
+    }
+
     #[test]
     fn basics() {
         let ast : AstNode = r"12".parse::<AstNode>().unwrap();
@@ -360,4 +678,159 @@ mod test {
         let ast2 = r"1(([1-9]([1-9])))*?".parse::<AstNode>().unwrap();
         assert!(ast2 == ast);
     }
-}
\ No newline at end of file
+
+    fn class_contains(node: &AstNode, c: char) -> bool {
+        match node {
+            Char(r) => r.contains(&(c as u32)),
+            Options(v) => v.iter().any(|n| class_contains(n, c)),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn properties() {
+        let ast = r"\p{L}".parse::<AstNode>().unwrap();
+        assert!(class_contains(&ast, 'λ'));
+        assert!(class_contains(&ast, '世'));
+        assert!(!class_contains(&ast, '1'));
+
+        let ast = r"\PL".parse::<AstNode>().unwrap();
+        assert!(!class_contains(&ast, 'λ'));
+        assert!(class_contains(&ast, '1'));
+
+        let ast = r"[\p{Nd}_]".parse::<AstNode>().unwrap();
+        assert!(class_contains(&ast, '٣'));
+        assert!(class_contains(&ast, '_'));
+        assert!(!class_contains(&ast, 'a'));
+
+        let ast = r"[\p{XID_Start}][\p{XID_Continue}]".parse::<AstNode>().unwrap();
+        if let Concat(v) = ast {
+            assert!(class_contains(&v[0], 'é') && !class_contains(&v[0], '0'));
+            assert!(class_contains(&v[1], '0'));
+        } else {
+            panic!("expected Concat");
+        }
+
+        assert!(matches!(r"\p{Nope}".parse::<AstNode>(), Err(Error::UnknownProperty(0, _))));
+        assert!(matches!(r"\p{L".parse::<AstNode>(), Err(Error::UnexpectedEnd(_))));
+    }
+
+    #[test]
+    fn escapes() {
+        let parse = |s: &str| s.parse::<AstNode>();
+
+        assert_eq!(parse(r"\x41").unwrap(), charnode('A'));
+        assert_eq!(parse(r"\u{3bb}").unwrap(), charnode('λ'));
+        assert_eq!(parse(r"\u{1F600}").unwrap(), charnode('😀'));
+        assert_eq!(parse(r"[\x00-\x1F]").unwrap(), charrange('\0', '\x1F'));
+        assert_eq!(parse(r"[\]\-]").unwrap(), Options(vec![charnode(']'), charnode('-')]));
+        assert_eq!(parse(r"\.").unwrap(), charnode('.'));
+        assert_eq!(parse(r"[+-]").unwrap(), Options(vec![charnode('+'), charnode('-')]));
+        assert_eq!(parse(r"[a-z-]").unwrap(), Options(vec![charrange('a', 'z'), charnode('-')]));
+
+        let ast = parse(r"\d").unwrap();
+        assert!(class_contains(&ast, '7') && !class_contains(&ast, 'a') && !class_contains(&ast, '٣'));
+        let ast = parse(r"\w").unwrap();
+        assert!(class_contains(&ast, '_') && class_contains(&ast, 'Z') && !class_contains(&ast, '-'));
+        let ast = parse(r"\s").unwrap();
+        assert!(class_contains(&ast, ' ') && class_contains(&ast, '\n') && !class_contains(&ast, 'x'));
+        let ast = parse(r"\S").unwrap();
+        assert!(!class_contains(&ast, '\t') && class_contains(&ast, 'x') && class_contains(&ast, '世'));
+        let ast = parse(r"[\D_]").unwrap();
+        assert!(!class_contains(&ast, '1') && class_contains(&ast, '_') && class_contains(&ast, 'a'));
+
+        assert!(matches!(parse(r"\x4"), Err(Error::UnexpectedEnd(3))));
+        assert!(matches!(parse(r"\xZZ"), Err(Error::UnexpectedChar(2, 'Z'))));
+        assert!(matches!(parse(r"\u41"), Err(Error::UnexpectedChar(2, '4'))));
+        assert!(matches!(parse(r"\u{41"), Err(Error::UnexpectedEnd(_))));
+        assert!(matches!(parse(r"\u{0000041}"), Err(Error::UnexpectedChar(9, '1'))));
+        assert!(matches!(parse(r"\u{110000}"), Err(Error::InvalidCodePoint(0))));
+        assert!(matches!(parse(r"\u{D800}"), Err(Error::InvalidCodePoint(0))));
+        assert!(matches!(parse(r"[z-a]"), Err(Error::InvalidRange(2))));
+        assert!(matches!(parse(r"[a-\d]"), Err(Error::InvalidRange(2))));
+        // a `-` with no char before it is a plain char.
+        assert_eq!(parse(r"[-+]").unwrap(), Options(vec![charnode('-'), charnode('+')]));
+        assert_eq!(parse(r"[^-]").unwrap(), parse(r"^\-").unwrap());
+        assert_eq!(parse(r"[\d-z]").unwrap(), Options(vec![charrange('0', '9'), charnode('-'), charnode('z')]));
+        assert_eq!(parse(r"[a-c-e]").unwrap(), Options(vec![charrange('a', 'c'), charnode('-'), charnode('e')]));
+        assert_eq!(parse(r"[--/]").unwrap(), charrange('-', '/'));
+        assert_eq!(parse(r"[\x41-C]").unwrap(), charrange('A', 'C'));
+    }
+
+    #[test]
+    fn negation() {
+        let parse = |s: &str| s.parse::<AstNode>();
+
+        let ast = parse(r#"[^"\\]"#).unwrap();
+        assert!(class_contains(&ast, 'a') && class_contains(&ast, '世') && class_contains(&ast, '\n'));
+        assert!(!class_contains(&ast, '"') && !class_contains(&ast, '\\'));
+
+        let ast = parse(r"[^a-z0-9_\s]").unwrap();
+        assert!(class_contains(&ast, 'A') && class_contains(&ast, '-'));
+        assert!(!class_contains(&ast, 'q') && !class_contains(&ast, '5') && !class_contains(&ast, ' '));
+
+        assert_eq!(parse(r"^[abc]").unwrap(), parse(r"[^abc]").unwrap());
+        assert_eq!(parse(r"^[^abc]").unwrap(), parse(r"[abc]").unwrap());
+        assert_eq!(parse(r"^a").unwrap(), Options(vec![Char(CHAR_MIN..'a' as u32), Char(add1('a')..CHAR_MAX)]));
+        assert_eq!(parse(r"^\x00").unwrap(), Char(1..CHAR_MAX));
+        assert_eq!(parse(r"[a^]").unwrap(), Options(vec![charnode('a'), charnode('^')]));
+
+        assert!(matches!(parse(r"[^\s\S]"), Err(Error::EmptyClass(0))));
+        assert!(matches!(parse(r"a^."), Err(Error::ExceptNotUsable(2))));
+        assert!(matches!(parse(r"[^]"), Err(Error::MissingExpresion(_))));
+    }
+
+    #[test]
+    fn nullable() {
+        let nullable = |s: &str| s.parse::<AstNode>().unwrap().is_nullable();
+        assert!(nullable(r"[0-9]*"));
+        assert!(nullable(r"a?(b|c*)"));
+        assert!(nullable(r"(a|b?)"));
+        assert!(!nullable(r"a*b"));
+        assert!(!nullable(r"[0-9]+"));
+        assert!(!nullable(r"a?b"));
+        assert!(nullable(r"a{0,3}"));
+        assert!(!nullable(r"a{1,}"));
+    }
+
+    #[test]
+    fn repeat() {
+        let parse = |s: &str| s.parse::<AstNode>();
+        let opt = |n: AstNode| EmptyOr(Box::new(n));
+
+        assert_eq!(parse(r"a{3}").unwrap(), Concat(vec![charnode('a'); 3]));
+        assert_eq!(parse(r"a{1}").unwrap(), charnode('a'));
+        assert_eq!(parse(r"a{2,}").unwrap(), Concat(vec![
+            charnode('a'), charnode('a'), multi(charnode('a')),
+        ]));
+        assert_eq!(parse(r"a{1,3}").unwrap(), Concat(vec![
+            charnode('a'),
+            opt(Concat(vec![charnode('a'), opt(charnode('a'))])),
+        ]));
+        assert_eq!(parse(r"[0-9]{2}").unwrap(), Concat(vec![charrange('0', '9'); 2]));
+        assert_eq!(parse(r"(ab){2}").unwrap(), Concat(vec![
+            Concat(vec![charnode('a'), charnode('b')]); 2
+        ]));
+
+        // not a repetition.
+        assert_eq!(parse(r"a{b").unwrap(), Concat(vec![charnode('a'), charnode('{'), charnode('b')]));
+        assert_eq!(parse(r"{").unwrap(), charnode('{'));
+
+        assert!(matches!(parse(r"a{3,1}"), Err(Error::InvalidRepeat(1))));
+        assert!(matches!(parse(r"a{1001}"), Err(Error::RepeatTooLarge(1))));
+        assert!(matches!(parse(r"a{99999999999999999999999}"), Err(Error::RepeatTooLarge(1))));
+        assert!(matches!(parse(r"(a{1000}){1000}"), Err(Error::RepeatTooLarge(9))));
+        assert!(matches!(parse(r"((a{10}){100}){1000}"), Err(Error::RepeatTooLarge(14))));
+        assert!(parse(r"(a{10}){100}").is_ok());
+        assert!(matches!(parse(r"a{2"), Err(Error::UnexpectedEnd(_))));
+        assert!(matches!(parse(r"a{2,x}"), Err(Error::UnexpectedChar(4, 'x'))));
+        assert!(matches!(parse(r"a{0}"), Err(Error::EmptyRepeat(1))));
+        assert!(matches!(parse(r"a{0,0}"), Err(Error::EmptyRepeat(1))));
+        assert_eq!(parse(r"a{0,1}").unwrap(), opt(charnode('a')));
+        // a count after another repetition.
+        assert!(matches!(parse(r"x{2}{3}"), Err(Error::UnexpectedChar(4, '{'))));
+        assert!(matches!(parse(r"a*{2}"), Err(Error::UnexpectedChar(2, '{'))));
+        assert!(matches!(parse(r"a+{2,}"), Err(Error::UnexpectedChar(2, '{'))));
+        assert_eq!(parse(r"a*{").unwrap(), Concat(vec![multi(charnode('a')), charnode('{')]));
+    }
+}
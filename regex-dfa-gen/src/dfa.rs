@@ -3,7 +3,7 @@ use sorted_vec::SortedVec;
 use crate::set::*;
 use crate::nfa::*;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque, BTreeSet};
 pub struct DfaBuilder<'a>{
     // SortedVec<usize> store nfa_states
     pub(crate) states: Vec<(DfaState, SortedVec<usize>)>,
@@ -17,6 +17,14 @@ pub struct DfaState {
     pub end_num: Option<usize>
 }
 
+/// a rule that never wins any dfa state, see `DfaBuilder::shadowed`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Shadowed {
+    pub end_num: usize,
+    /// the rule that wins instead, and an input matched by both.
+    pub by: Option<(usize, String)>,
+}
+
 
 impl<'a> DfaBuilder<'a> {
     fn new(nfa: &'a Nfa) -> Self {
@@ -37,25 +45,31 @@ impl<'a> DfaBuilder<'a> {
         if let Some(index) = ret {
             index
         } else {
-            let iter: _ = nfa_states.iter().flat_map(|nfa_state| &self.nfa.states[*nfa_state].table).copied();
-            let maps: _ = self.iter_to_map(iter);
+            let iter = nfa_states.iter().flat_map(|nfa_state| &self.nfa.states[*nfa_state].table).copied();
+            let maps = self.iter_to_map(iter);
             self.build_from_vec(nfa_states, maps)
         }
     }
 
-    fn iter_to_map(&self, targets: impl Iterator<Item=usize>) -> RangeMap::<char, usize> {
-        let mut maps = RangeMap::<char, usize>::new();
+    fn iter_to_map(&self, targets: impl Iterator<Item=usize>) -> RangeMap::<u32, usize> {
+        let mut maps = RangeMap::<u32, usize>::new();
         for i in targets {
             let target = &self.nfa.states[i];
-            maps.insert(target.ch.clone(), i);
+            for ch in &target.ch {
+                maps.insert(ch.clone(), i);
+            }
         }
         maps
     }
 
     fn state_init(&mut self, nfa_states: SortedVec<usize>) -> usize {
         let end_num = nfa_states.iter()
-        .filter_map(|&x| self.nfa.states[x].end_num)
-        .max_by(|x,y| x.cmp(y));
+        .filter_map(|&x| {
+            let state = &self.nfa.states[x];
+            state.end_num.map(|n| (state.priority, n))
+        })
+        .max_by(|(p1, n1), (p2, n2)| p1.cmp(p2).then(n2.cmp(n1)))
+        .map(|(_, n)| n);
         self.push(
             DfaState{
                 table: Vec::new(),
@@ -66,7 +80,7 @@ impl<'a> DfaBuilder<'a> {
     }
 
     #[inline]
-    fn build_from_vec(&mut self, nfa_states: SortedVec<usize>, vec: RangeMap::<char, usize>) -> usize {
+    fn build_from_vec(&mut self, nfa_states: SortedVec<usize>, vec: RangeMap::<u32, usize>) -> usize {
         let vec = vec.0;
         let ret = self.state_init(nfa_states);
 
@@ -74,8 +88,8 @@ impl<'a> DfaBuilder<'a> {
         for (k, v) in vec {
             let mut v = SortedVec::from_unsorted(v);
             v.dedup();
-            let is_greedy = v.iter().map(|&i| self.nfa.states[i].is_greedy)
-                .fold(false, |a,b| a | b);
+            // an accepting state stops at any arc that can continue a `*?`.
+            let is_greedy = v.iter().all(|&i| self.nfa.states[i].is_greedy);
             table.push((
                 k, self.build(v), is_greedy
             ))
@@ -86,12 +100,53 @@ impl<'a> DfaBuilder<'a> {
     }
     /// get the builder from nfa.
     pub fn from_nfa(nfa: &'a Nfa) -> Self {
-        let mut ret = Self::new(&nfa);
+        let mut ret = Self::new(nfa);
         let maps = ret.iter_to_map(nfa.node.0.iter().copied());
         ret.build_from_vec(SortedVec::new(), maps);
         ret
     }
     
+    /// find the rules (`end_num`s set on the nfa) that can never be returned,
+    /// since every input they match is taken by another rule.
+    pub fn shadowed(&self) -> Vec<Shadowed> {
+        let rules: BTreeSet<usize> = self.nfa.states.iter()
+            .filter_map(|s| s.end_num)
+            .collect();
+        let winners: BTreeSet<usize> = self.states.iter()
+            .filter_map(|(s, _)| s.end_num)
+            .collect();
+
+        // shortest input reaching each state.
+        let mut examples: Vec<Option<String>> = vec![None; self.states.len()];
+        let mut queue = VecDeque::new();
+        examples[0] = Some(String::new());
+        queue.push_back(0);
+        let mut order = Vec::new();
+        while let Some(i) = queue.pop_front() {
+            order.push(i);
+            for (range, j, _) in &self.states[i].0.table {
+                if examples[*j].is_none() {
+                    if let Some(c) = sample_char(range) {
+                        let mut example = examples[i].clone().unwrap();
+                        example.push(c);
+                        examples[*j] = Some(example);
+                        queue.push_back(*j);
+                    }
+                }
+            }
+        }
+
+        rules.difference(&winners).map(|&end_num| {
+            let by = order.iter().find(|&&i| {
+                self.states[i].1.iter().any(|&x| self.nfa.states[x].end_num == Some(end_num))
+            }).and_then(|&i| {
+                let example = examples[i].clone().unwrap();
+                self.states[i].0.end_num.map(|by| (by, example))
+            });
+            Shadowed { end_num, by }
+        }).collect()
+    }
+
     /// get the dfa.
     pub fn to_dfa(self) -> Dfa {
         Dfa {
@@ -104,6 +159,14 @@ impl<'a> DfaBuilder<'a> {
 }
 
 
+/// a char of `range` to show in examples, preferring ascii letters and digits.
+fn sample_char(range: &CharRange) -> Option<char> {
+    let bounds = char_bounds(range)?;
+    ('0'..='z').filter(|c| c.is_ascii_alphanumeric())
+        .find(|c| bounds.contains(c))
+        .or(Some(*bounds.start()))
+}
+
 pub struct Dfa {
     pub states: Vec<DfaState>,
 }
@@ -134,7 +197,7 @@ impl Dfa {
     }
 
     pub fn from_nfa(nfa: &Nfa) -> Dfa {
-        DfaBuilder::from_nfa(&nfa).to_dfa()
+        DfaBuilder::from_nfa(nfa).to_dfa()
     }
     pub fn replace(self, pair: HashMap<usize, usize>) -> Dfa {
         let mut ret = Vec::new();
@@ -171,8 +234,7 @@ impl Dfa {
             }
             maps.insert(state, i);
         }
-        let ret = self.replace(pair);
-        ret
+        self.replace(pair)
     }
 }
 
@@ -193,7 +255,7 @@ impl<'a> dot::Labeller<'a, Nd, Ed> for Dfa {
         }
     }
     fn edge_label<'b>(&'b self, (_, _, ch, _): &Ed) -> dot::LabelText<'b> {
-        dot::LabelText::LabelStr(format!("{}", show_char_range(ch.clone())).into())
+        dot::LabelText::LabelStr(show_char_range(ch.clone()).into())
     }
     fn edge_color<'b>(&'b self, (_, _, _, is_greedy): &Ed) -> Option<dot::LabelText<'b>>{
         if *is_greedy {
@@ -207,7 +269,7 @@ impl<'a> dot::Labeller<'a, Nd, Ed> for Dfa {
 impl<'a> dot::GraphWalk<'a, Nd, Ed> for Dfa {
     fn nodes(&self) -> dot::Nodes<'a,Nd> {
         let nodes: Vec<_> = self.states.iter().enumerate()
-            .map(|(i,state)| (i, state.end_num.clone()))
+            .map(|(i,state)| (i, state.end_num))
             .collect();
         Cow::Owned(nodes)
     }
@@ -249,4 +311,67 @@ mod test {
         assert_eq!(nfa.states.len(), 4);
         assert_eq!(dfa.states.len(), 6);
     }
-}
\ No newline at end of file
+
+    fn end_num_of(dfa: &Dfa, s: &str) -> Option<usize> {
+        let mut state = 0;
+        for c in s.chars() {
+            state = dfa.states[state].table.iter()
+                .find(|(range, _, _)| range.contains(&(c as u32)))?.1;
+        }
+        dfa.states[state].end_num
+    }
+
+    fn build(rules: &[(&str, i32)]) -> Dfa {
+        let mut builder = NfaBuilder::new();
+        let nodes = rules.iter().enumerate().map(|(i, (regex, priority))| {
+            let node = builder.from_ast(&regex.parse::<AstNode>().unwrap());
+            builder.set_end_with_priority(&node, i, *priority);
+            node
+        }).collect();
+        let node = builder.options(nodes);
+        Dfa::from_nfa(&builder.to_nfa(node))
+    }
+
+    #[test]
+    fn priority() {
+        let dfa = build(&[("if", 0), ("[a-z]+", 0)]);
+        assert_eq!(end_num_of(&dfa, "if"), Some(0));
+        assert_eq!(end_num_of(&dfa, "iff"), Some(1));
+
+        let dfa = build(&[("[a-z]+", 0), ("if", 0)]);
+        assert_eq!(end_num_of(&dfa, "if"), Some(0));
+
+        let dfa = build(&[("[a-z]+", 0), ("if", 1)]);
+        assert_eq!(end_num_of(&dfa, "if"), Some(1));
+        assert_eq!(end_num_of(&dfa, "i"), Some(0));
+    }
+
+    #[test]
+    fn repeat() {
+        let dfa = build(&[("[0-9]{2,3}", 0), ("[0-9a-f]{4}", 0)]);
+        assert_eq!(end_num_of(&dfa, "1"), None);
+        assert_eq!(end_num_of(&dfa, "12"), Some(0));
+        assert_eq!(end_num_of(&dfa, "123"), Some(0));
+        assert_eq!(end_num_of(&dfa, "1234"), Some(1));
+        assert_eq!(end_num_of(&dfa, "12a"), None);
+        assert_eq!(end_num_of(&dfa, "12ab"), Some(1));
+        assert_eq!(end_num_of(&dfa, "12345"), None);
+    }
+
+    #[test]
+    fn shadowed() {
+        let mut builder = NfaBuilder::new();
+        let nodes = ["[a-z]+", "if", "[0-9]", "[5-6]"].iter().enumerate().map(|(i, regex)| {
+            let node = builder.from_ast(&regex.parse::<AstNode>().unwrap());
+            builder.set_end(&node, i);
+            node
+        }).collect();
+        let node = builder.options(nodes);
+        let nfa = builder.to_nfa(node);
+        let shadowed = DfaBuilder::from_nfa(&nfa).shadowed();
+        assert_eq!(shadowed, vec![
+            Shadowed { end_num: 1, by: Some((0, "if".into())) },
+            Shadowed { end_num: 3, by: Some((2, "5".into())) },
+        ]);
+    }
+}
@@ -7,3 +7,4 @@ pub mod ast;
 pub mod dfa;
 pub mod nfa;
 pub mod set;
+pub mod unicode;
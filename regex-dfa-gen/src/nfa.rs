@@ -26,23 +26,33 @@ pub struct NfaBuilder {
 /// can only use to a single builder.(will be improved)
 pub struct NfaStateNode(pub(crate) Vec<usize>, Vec<usize>, bool);
 
+impl NfaStateNode {
+    /// whether the node matches the empty string.
+    pub fn is_nullable(&self) -> bool {
+        self.2
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct NfaState {
-    pub(crate) ch : CharRange,
+    // a char class is kept in a single state.
+    pub(crate) ch : Vec<CharRange>,
     pub(crate) table: Vec<usize>,
     pub(crate) is_greedy: bool,
     pub(crate) end_num: Option<usize>,
+    pub(crate) priority: i32,
 }
 
 
 impl NfaState {
     #[inline]
-    pub fn new(ch: CharRange, is_greedy: bool) -> Self {
+    pub fn new(ch: Vec<CharRange>, is_greedy: bool) -> Self {
         Self {
             ch,
             table: Vec::new(),
             is_greedy,
-            end_num: None
+            end_num: None,
+            priority: 0,
         }
     }
 }
@@ -56,7 +66,7 @@ impl NfaBuilder {
     }
 
     #[inline]
-    fn push(&mut self,ch: CharRange, is_greedy: bool) -> usize {
+    fn push(&mut self,ch: Vec<CharRange>, is_greedy: bool) -> usize {
         let ret = self.states.len();
         self.states.push(NfaState::new(ch, is_greedy));
         ret
@@ -69,11 +79,23 @@ impl NfaBuilder {
         }
     }
     // returns can it be epsilon.
+    //
+    // states are greedy unless they come from a non-greedy `*?`.
     fn build_from(&mut self, node: &AstNode, head: &mut Vec<usize>, tail: &mut Vec<usize>, is_greedy: bool) -> bool {
         use AstNode::*;
         match node {
             Char(ch) => {
-                let id = self.push(ch.clone(), is_greedy);
+                let id = self.push(vec![ch.clone()], is_greedy);
+                head.push(id);
+                tail.push(id);
+                false
+            }
+            Options(vec) if vec.iter().all(|n| matches!(n, Char(_))) => {
+                let chars = vec.iter().filter_map(|n| match n {
+                    Char(ch) => Some(ch.clone()),
+                    _ => None,
+                }).collect();
+                let id = self.push(chars, is_greedy);
                 head.push(id);
                 tail.push(id);
                 false
@@ -89,7 +111,7 @@ impl NfaBuilder {
                 let hlen = head.len();
                 let tlen = tail.len();
                 // for `Multiple`, it can be epsilon.
-                self.build_from(n, head, tail, true);
+                self.build_from(n, head, tail, is_greedy);
                 self.state_extend_all(&tail[tlen..], &head[hlen..]);
                 true
             }
@@ -113,7 +135,7 @@ impl NfaBuilder {
                 for n in vec {
                     if first {
                         let hlen = head.len();
-                        let can_be_eps = self.build_from(n, head, &mut tmp, false);
+                        let can_be_eps = self.build_from(n, head, &mut tmp, is_greedy);
                         first &= can_be_eps;
                         self.state_extend_all(&tmp_tail[..], &head[hlen..]);
                         if !can_be_eps {
@@ -122,7 +144,7 @@ impl NfaBuilder {
                         tmp_tail.append(&mut tmp);
                         tmp.clear();
                     } else {
-                        let can_be_eps = self.build_from(n, &mut tmp_head, &mut tmp, false);
+                        let can_be_eps = self.build_from(n, &mut tmp_head, &mut tmp, is_greedy);
                         self.state_extend_all(&tmp_tail[..], &tmp_head[..]);
                         
                         if !can_be_eps {
@@ -142,14 +164,22 @@ impl NfaBuilder {
     pub fn from_ast(&mut self, ast: &AstNode) -> NfaStateNode {
         let mut head = Vec::new();
         let mut tail = Vec::new();
-        let can_be_eps = self.build_from(ast, &mut head, &mut tail, false);
+        let can_be_eps = self.build_from(ast, &mut head, &mut tail, true);
         NfaStateNode(head, tail, can_be_eps)
     }
-    /// set the end at the end of Nfa nodes.
+    /// set the end at the end of Nfa nodes, with priority 0.
     pub fn set_end(&mut self, node: &NfaStateNode, end_num: usize) {
+        self.set_end_with_priority(node, end_num, 0);
+    }
+    /// set the end at the end of Nfa nodes.
+    ///
+    /// if a dfa state ends several nodes, the highest `priority` wins, then
+    /// the lowest `end_num`.
+    pub fn set_end_with_priority(&mut self, node: &NfaStateNode, end_num: usize, priority: i32) {
         for &ind in &node.1 {
             let state = &mut self.states[ind];
             state.end_num = Some(end_num);
+            state.priority = priority;
         }
     }
     /// equal to regex `( node1 | node2 ... )`.
@@ -174,8 +204,16 @@ impl NfaBuilder {
     pub fn len(&self) -> usize {
         self.states.len()
     }
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
 }
 
+impl Default for NfaBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Nfa {
     pub fn from_ast(ast: &AstNode) -> Self {
@@ -190,6 +228,14 @@ impl Nfa {
     pub fn len(&self) -> usize {
         self.states.len()
     }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+    /// whether the nfa matches the empty string.
+    pub fn accepts_empty(&self) -> bool {
+        self.node.is_nullable()
+    }
 }
 
 
@@ -231,7 +277,7 @@ impl<'a> dot::Labeller<'a, Nd, Ed> for Nfa {
         let is_end = self.node.1.iter().find(|&x| x == n).is_some();
         let is_end = if is_end {"(e)"} else { "" };
 
-        dot::LabelText::LabelStr(format!("{}{}{}", show_char_range(state.ch.clone()), is_start, is_end).into())
+        dot::LabelText::LabelStr(format!("{}{}{}", state.ch.iter().cloned().map(show_char_range).collect::<Vec<_>>().join("|"), is_start, is_end).into())
     }
 }
 
@@ -265,13 +311,26 @@ mod test {
         assert_eq!(nfa.states.len(), 2);
 
 
+        // `(5|4)` is a char class and takes a single state.
         let ast : AstNode = r"1|2*3(5|4)*".parse::<AstNode>().unwrap();
         let nfa = Nfa::from_ast(&ast);
-        assert_eq!(nfa.states.len(), 5);
+        assert_eq!(nfa.states.len(), 4);
 
         
         let ast : AstNode = r"([A-Za-z])(1?|2*3?(5|4)*)(e)".parse::<AstNode>().unwrap();
         let nfa = Nfa::from_ast(&ast);
-        assert_eq!(nfa.states.len(), 8);
+        assert_eq!(nfa.states.len(), 6);
+
+        let ast : AstNode = r"\p{XID_Start}\p{XID_Continue}*".parse::<AstNode>().unwrap();
+        let nfa = Nfa::from_ast(&ast);
+        assert_eq!(nfa.states.len(), 2);
+    }
+
+    #[test]
+    fn accepts_empty() {
+        for (regex, empty) in [("a*", true), ("(a|b?)c?", true), ("a*b", false), ("a|b", false)].iter() {
+            let ast : AstNode = regex.parse::<AstNode>().unwrap();
+            assert_eq!(Nfa::from_ast(&ast).accepts_empty(), *empty, "{}", regex);
+        }
     }
 }
\ No newline at end of file
@@ -1,15 +1,45 @@
-use std::ops::Range;
+use std::ops::{Range, RangeInclusive};
 use std::fmt::Debug;
-pub type CharRange = Range<char>;
 
-pub const CHAR_MAX : char = 127 as char;
-pub const CHAR_MIN : char = 0 as char;
+/// a range of unicode code points.
+///
+/// stored as `u32` rather than `char` so that the exclusive end can reach
+/// past `char::MAX`.
+pub type CharRange = Range<u32>;
 
-pub fn add1(c : char) -> char {
-    (c as u8 + 1) as char
+/// exclusive upper bound of the `char` domain.
+pub const CHAR_MAX : u32 = char::MAX as u32 + 1;
+pub const CHAR_MIN : u32 = 0;
+
+/// surrogate code points, which are never a valid `char`.
+pub const SURROGATES : CharRange = 0xD800..0xE000;
+
+/// the code point right after `c`.
+pub fn add1(c : char) -> u32 {
+    c as u32 + 1
+}
+
+/// the range matching `c` only.
+pub fn char_range(c : char) -> CharRange {
+    c as u32..add1(c)
 }
-pub fn sub1(c : char) -> char {
-    (c as u8 + 1) as char
+
+/// inclusive `char` bounds of `range`, with surrogates trimmed from both ends.
+///
+/// returns `None` if the range holds no valid `char`.
+pub fn char_bounds(range: &CharRange) -> Option<RangeInclusive<char>> {
+    let mut start = range.start;
+    let mut end = std::cmp::min(range.end, CHAR_MAX);
+    if SURROGATES.contains(&start) {
+        start = SURROGATES.end;
+    }
+    if SURROGATES.start < end && end <= SURROGATES.end {
+        end = SURROGATES.start;
+    }
+    if start >= end {
+        return None;
+    }
+    Some(std::char::from_u32(start)?..=std::char::from_u32(end - 1)?)
 }
 
 #[inline(always)]
@@ -71,6 +101,12 @@ enum SplitResult<T> {
 #[derive(Debug, Clone)]
 pub struct RangeMap<K, V>(pub Vec<(Range<K>, Vec<V>)>);
 
+impl<K: Copy + Ord, V: Clone> Default for RangeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<K: Copy + Ord, V: Clone> RangeMap<K, V> {
     pub fn new() -> Self {
         Self(Vec::new())
@@ -80,12 +116,12 @@ impl<K: Copy + Ord, V: Clone> RangeMap<K, V> {
         use SplitResult::*;
         if range_is_empty(&range) {return;}
 
-        let mut iter = self.0.iter_mut();
+        let iter = self.0.iter_mut();
         let mut tmpvec : Vec<(Range<K>, Vec<V>)> = Vec::new();
         let mut tmp_range :Option<Range<K>> = None;
         let mut range = Some(range.clone());
 
-        while let Some((r, vec)) = iter.next() {
+        for (r, vec) in iter {
             match range.clone().and_then(|ran| inter_split(r.clone(), ran)) {
                 Some(FirstSecond(f, inter, s)) => {
                     // println!("FirstSecond {:?} {:?} {:?}", f, inter, s);
@@ -139,7 +175,37 @@ impl<K: Copy + Ord, V: Clone> RangeMap<K, V> {
     }
 }
 
+/// the complement of `ranges` over the whole `char` domain.
+///
+/// `ranges` need not be sorted or disjoint.
+pub fn complement(ranges: &[CharRange]) -> Vec<CharRange> {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_by_key(|r| r.start);
+
+    let mut ret = Vec::new();
+    let mut next = CHAR_MIN;
+    for r in sorted {
+        if next < r.start {
+            ret.push(next..r.start);
+        }
+        next = std::cmp::max(next, r.end);
+    }
+    if next < CHAR_MAX {
+        ret.push(next..CHAR_MAX);
+    }
+    ret
+}
+
+pub fn show_char_range(ch : CharRange) -> String {
+    match char_bounds(&ch) {
+        Some(r) if r.start() == r.end() => format!("{}", r.start().escape_debug()),
+        Some(r) => format!("[{}-{}]", r.start().escape_debug(), r.end().escape_debug()),
+        None => String::new(),
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::almost_complete_range)]
 mod test {
     use super::*;
     use std::assert_eq;
@@ -225,12 +291,25 @@ mod test {
             ]
         }
     }
-}
 
-pub fn show_char_range(ch : CharRange) -> String {
-    if ch.end as u8 - ch.start as u8 == 1 {
-        format!("{}", ch.start)
-    } else {
-        format!("[{}-{}]", ch.start, (ch.end as u8 - 1) as char)
+    #[test]
+    fn unicode_bounds() {
+        assert_eq!(add1('\u{7f}'), 0x80);
+        assert_eq!(char_bounds(&(CHAR_MIN..CHAR_MAX)), Some('\0'..=char::MAX));
+        assert_eq!(char_bounds(&(0xD000..0xD900)), Some('\u{D000}'..='\u{D7FF}'));
+        assert_eq!(char_bounds(&(0xDA00..0xE001)), Some('\u{E000}'..='\u{E000}'));
+        assert_eq!(char_bounds(&SURROGATES), None);
+        assert_eq!(show_char_range(char_range('λ')), "λ");
+        assert_eq!(show_char_range(0x61..0x7B), "[a-z]");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn complement_ranges() {
+        assert_eq!(complement(&[]), vec![CHAR_MIN..CHAR_MAX]);
+        assert_eq!(
+            complement(&[0x61..0x63, 0x30..0x3A, 0x62..0x64]),
+            vec![CHAR_MIN..0x30, 0x3A..0x61, 0x64..CHAR_MAX]
+        );
+        assert_eq!(complement(&[CHAR_MIN..0x100, 0x100..CHAR_MAX]), vec![]);
+    }
+}
@@ -0,0 +1,29 @@
+//! Unicode property classes, as used by `\p{..}` in regex.
+//!
+//! ```
+//! use regex_dfa_gen::unicode::property_ranges;
+//! let digits = property_ranges("Nd").unwrap();
+//! assert!(digits.iter().any(|r| r.contains(&('٣' as u32))));
+//! ```
+
+use crate::set::*;
+use regex_syntax::hir::{Class, HirKind};
+
+/// ranges of the unicode property `name`, as written inside `\p{..}`.
+///
+/// general categories (`L`, `Nd`, ...), scripts (`Greek`) and binary
+/// properties (`XID_Start`, `XID_Continue`, ...) are accepted.
+/// returns `None` if `name` is not a known property.
+pub fn property_ranges(name: &str) -> Option<Vec<CharRange>> {
+    let hir = regex_syntax::Parser::new()
+        .parse(&format!(r"\p{{{}}}", name))
+        .ok()?;
+    match hir.kind() {
+        HirKind::Class(Class::Unicode(class)) => Some(
+            class.ranges().iter()
+                .map(|r| r.start() as u32..add1(r.end()))
+                .collect()
+        ),
+        _ => None,
+    }
+}